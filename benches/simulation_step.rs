@@ -0,0 +1,143 @@
+use ant::consts::{BOARD_RADIUS, SEED, VISION_RADIUS};
+use ant::simulation::ant::MovementModel;
+use ant::simulation::board::{Board, BoardTopology, IcoBoard};
+use ant::simulation::state::SimulationState;
+use bevy::prelude::*;
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion};
+
+const BOARD_SUBDIVISIONS: [usize; 3] = [8, 16, 32];
+const ANT_COUNTS: [usize; 3] = [100, 1_000, 10_000];
+const VISION_RADII: [usize; 3] = [1, 2, 3];
+/// Fraction of the board cells holding an item
+const ITEM_DENSITY: f64 = 0.3;
+
+fn setup_state(subdivisions: usize, ant_count: usize, vision_radius: usize) -> SimulationState {
+    let board = Box::new(IcoBoard::from_mesh(
+        &Mesh::from(shape::Icosphere {
+            radius: BOARD_RADIUS,
+            subdivisions,
+        }),
+        vision_radius,
+    ));
+    let item_count = (board.size() as f64 * ITEM_DENSITY) as usize;
+    let mut state = SimulationState::new(board, SEED);
+    state.spawn_ants(0, ant_count);
+    state.spawn_items(item_count);
+    state
+}
+
+fn fixed_step(c: &mut Criterion) {
+    let mut group = c.benchmark_group("fixed_step");
+    for subdivisions in BOARD_SUBDIVISIONS {
+        for ant_count in ANT_COUNTS {
+            let mut state = setup_state(subdivisions, ant_count, VISION_RADIUS);
+            group.bench_with_input(
+                BenchmarkId::new(format!("subdivisions_{subdivisions}"), ant_count),
+                &ant_count,
+                |b, _| b.iter(|| state.step(false)),
+            );
+        }
+    }
+    group.finish();
+}
+
+fn vision_radius(c: &mut Criterion) {
+    let mut group = c.benchmark_group("vision_radius");
+    for vision_radius in VISION_RADII {
+        let mut state = setup_state(16, 1_000, vision_radius);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(vision_radius),
+            &vision_radius,
+            |b, _| b.iter(|| state.step(false)),
+        );
+    }
+    group.finish();
+}
+
+fn board_setup(c: &mut Criterion) {
+    let mut group = c.benchmark_group("board_setup");
+    for subdivisions in BOARD_SUBDIVISIONS {
+        let mesh = Mesh::from(shape::Icosphere {
+            radius: BOARD_RADIUS,
+            subdivisions,
+        });
+        group.bench_with_input(
+            BenchmarkId::from_parameter(subdivisions),
+            &mesh,
+            |b, mesh| b.iter(|| IcoBoard::from_mesh(mesh, VISION_RADIUS)),
+        );
+    }
+    group.finish();
+}
+
+fn topology(c: &mut Criterion) {
+    let mut group = c.benchmark_group("topology");
+    for topology in [
+        BoardTopology::IcoSphere,
+        BoardTopology::Square,
+        BoardTopology::Torus,
+        BoardTopology::Hex,
+    ] {
+        let board = topology.build(VISION_RADIUS).unwrap();
+        let item_count = (board.size() as f64 * ITEM_DENSITY) as usize;
+        let mut state = SimulationState::new(board, SEED);
+        state.spawn_ants(0, 1_000);
+        state.spawn_items(item_count);
+        group.bench_with_input(
+            BenchmarkId::from_parameter(format!("{topology:?}")),
+            &topology,
+            |b, _| b.iter(|| state.step(false)),
+        );
+    }
+    group.finish();
+}
+
+fn movement(c: &mut Criterion) {
+    let mut group = c.benchmark_group("movement");
+    for name in ["uniform", "correlated", "levy", "jump"] {
+        let mut state = setup_state(16, 1_000, VISION_RADIUS);
+        state.set_movement(name.parse::<MovementModel>().unwrap());
+        group.bench_with_input(BenchmarkId::from_parameter(name), &name, |b, _| {
+            b.iter(|| state.step(false))
+        });
+    }
+    group.finish();
+}
+
+fn memory(c: &mut Criterion) {
+    let mut group = c.benchmark_group("memory");
+    for size in [0, 8] {
+        let mut state = setup_state(16, 1_000, VISION_RADIUS);
+        state.memory.size = size;
+        group.bench_with_input(BenchmarkId::from_parameter(size), &size, |b, _| {
+            b.iter(|| state.step(false))
+        });
+    }
+    group.finish();
+}
+
+/// Colonies seeing another radius than the board, using the vision added for them when spawning
+fn colony_vision(c: &mut Criterion) {
+    let mut group = c.benchmark_group("colony_vision");
+    for radius in VISION_RADII {
+        let mut state = setup_state(16, 0, VISION_RADIUS);
+        state.colonies[0].config.vision_radius = radius;
+        state.spawn_ants(0, 1_000);
+        group.bench_with_input(BenchmarkId::from_parameter(radius), &radius, |b, _| {
+            b.iter(|| state.step(false))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    fixed_step,
+    vision_radius,
+    topology,
+    movement,
+    memory,
+    colony_vision,
+    board_setup
+);
+criterion_main!(benches);
@@ -0,0 +1,170 @@
+//! Runs the simulation without rendering
+//!
+//! Usage: `cargo run --release --bin headless -- [steps] [seed] [board] [movement] [memory] [drop]`
+//!
+//! `movement` replaces the movement model of every colony and `memory` is the number
+//! of cells remembered by each ant, zero disabling the memory.
+//! `drop` replaces the drop policy of every colony, one of own, random or densest.
+//! When `FORAGING` is set, the food delivered to the nests is reported instead of the density.
+//! The energy model adds the living ants and the births and deaths so far, and with several
+//! colonies each one adds its own density, carrying ants, pickups and drops.
+//! When the parameters of the ants vary, the mean and standard deviation of each one are added
+
+use ant::consts::{ANT_MEMORY, BOARD_TOPOLOGY, FORAGING, ITEM_COUNT, SEED};
+use ant::simulation::ant::{AntParameters, MovementModel};
+use ant::simulation::board::BoardTopology;
+use ant::simulation::colony::DropPolicy;
+use ant::simulation::state::SimulationState;
+use std::time::Instant;
+
+const DEFAULT_STEPS: u64 = 100_000;
+const REPORT_INTERVAL: u64 = 10_000;
+
+fn main() {
+    let mut args = std::env::args().skip(1);
+    let steps = args
+        .next()
+        .map(|arg| arg.parse().expect("steps must be a positive integer"))
+        .unwrap_or(DEFAULT_STEPS);
+    let seed = args
+        .next()
+        .map(|arg| arg.parse().expect("seed must be a positive integer"))
+        .unwrap_or(SEED);
+    let topology = args
+        .next()
+        .map(|arg| {
+            arg.parse::<BoardTopology>()
+                .unwrap_or_else(|e| panic!("{e}"))
+        })
+        .unwrap_or(BOARD_TOPOLOGY);
+    let movement = args.next().map(|arg| {
+        arg.parse::<MovementModel>()
+            .unwrap_or_else(|e| panic!("{e}"))
+    });
+    let memory_size = args
+        .next()
+        .map(|arg| arg.parse().expect("memory must be a positive integer"))
+        .unwrap_or(ANT_MEMORY.size);
+    let drop_policy = args
+        .next()
+        .map(|arg| arg.parse::<DropPolicy>().unwrap_or_else(|e| panic!("{e}")));
+
+    let board = topology
+        .build_configured(topology.default_resolution())
+        .unwrap_or_else(|e| panic!("{e}"));
+    let mut state = SimulationState::new(board, seed);
+    if let Some(movement) = movement {
+        state.set_movement(movement);
+    }
+    if let Some(drop_policy) = drop_policy {
+        state.set_drop_policy(drop_policy);
+    }
+    state.memory.size = memory_size;
+    let mut extra_header = String::new();
+    if state.lifecycle.is_some() {
+        extra_header.push_str(",population,births,deaths");
+    }
+    if state.disturbance.is_some() {
+        extra_header.push_str(",items_on_board,added,decayed,removed");
+    }
+    if state.crowding.is_some() {
+        extra_header.push_str(",blocked,exchanges");
+    }
+    if state.has_varying_parameters() {
+        for name in AntParameters::NAMES {
+            extra_header.push_str(&format!(",{name}_mean,{name}_std_dev"));
+        }
+    }
+    if state.colonies.len() > 1 {
+        for colony in &state.colonies {
+            let name = colony.config.name;
+            extra_header.push_str(&format!(
+                ",{name}_density,{name}_carrying,{name}_pickups,{name}_drops"
+            ));
+        }
+    }
+    if let Some(config) = FORAGING {
+        state.start_foraging(config);
+        state.spawn_colonies();
+        state.spawn_food_sources();
+        println!(
+            "step,food_delivered,delivered_per_step,delivered_last_step,elapsed_secs{extra_header}"
+        );
+    } else {
+        state.spawn_colonies();
+        state.spawn_items(ITEM_COUNT);
+        println!("step,mean_item_density,elapsed_secs{extra_header}");
+    }
+
+    let start = Instant::now();
+    let mut last_delivered = 0;
+    for _ in 0..steps {
+        state.step(false);
+        if state.step_count % REPORT_INTERVAL != 0 {
+            continue;
+        }
+        if let Some(foraging) = &state.foraging {
+            println!(
+                "{},{},{:.4},{},{:.3}{}",
+                state.step_count,
+                foraging.delivered,
+                (foraging.delivered - last_delivered) as f64 / REPORT_INTERVAL as f64,
+                foraging.delivered_last_step,
+                start.elapsed().as_secs_f64(),
+                extra_columns(&state)
+            );
+            last_delivered = foraging.delivered;
+        } else {
+            println!(
+                "{},{:.4},{:.3}{}",
+                state.step_count,
+                state.mean_item_density(),
+                start.elapsed().as_secs_f64(),
+                extra_columns(&state)
+            );
+        }
+    }
+}
+
+/// Columns of the optional models and of every colony, when they are reported
+fn extra_columns(state: &SimulationState) -> String {
+    let mut columns = String::new();
+    if let Some(lifecycle) = &state.lifecycle {
+        columns.push_str(&format!(
+            ",{},{},{}",
+            state.population(),
+            lifecycle.births,
+            lifecycle.deaths
+        ));
+    }
+    if let Some(disturbance) = &state.disturbance {
+        columns.push_str(&format!(
+            ",{},{},{},{}",
+            state.items_on_board(),
+            disturbance.added,
+            disturbance.decayed,
+            disturbance.removed
+        ));
+    }
+    if let Some(crowding) = &state.crowding {
+        columns.push_str(&format!(",{},{}", crowding.blocked, crowding.exchanges));
+    }
+    if state.has_varying_parameters() {
+        for summary in state.parameter_summaries() {
+            columns.push_str(&format!(",{:.4},{:.4}", summary.mean, summary.std_dev));
+        }
+    }
+    if state.colonies.len() > 1 {
+        for (i, colony) in state.colonies.iter().enumerate() {
+            let (_, carrying) = state.colony_ant_counts(i);
+            columns.push_str(&format!(
+                ",{:.4},{},{},{}",
+                state.colony_item_density(i),
+                carrying,
+                colony.pickups,
+                colony.drops
+            ));
+        }
+    }
+    columns
+}
@@ -1,18 +1,120 @@
+use bevy::prelude::Color;
+
+use crate::simulation::aco::AcoConfig;
+use crate::simulation::ant::{
+    AntParameterConfig, MemoryConfig, MovementModel, ParameterDistribution, ProbabilityModel,
+};
+use crate::simulation::board::{
+    BoardTopology, Obstacle, PheromoneConfig, Terrain, TerrainMovement,
+};
+use crate::simulation::colony::{ColonyConfig, DropPolicy};
+use crate::simulation::crowding::CrowdingConfig;
+use crate::simulation::disturbance::DisturbanceConfig;
+use crate::simulation::foraging::ForagingConfig;
+use crate::simulation::lifecycle::LifecycleConfig;
+
 // Window setup
 pub const CAMERA_DISTANCE: f32 = 16.0;
 pub const WINDOW_SIZE: f32 = 800.;
 pub const STARTING_UPS: f64 = 1. / 10.;
 
+// Simulation
+pub const SEED: u64 = 42;
+
 // Board
+pub const BOARD_TOPOLOGY: BoardTopology = BoardTopology::IcoSphere;
 pub const BOARD_SUBDIVISIONS: usize = 16;
 pub const BOARD_RADIUS: f32 = 5.0;
+/// Cells per side of the flat boards
+pub const GRID_SIZE: usize = 40;
+/// Regions blocked when the board is built
+pub const OBSTACLES: &[Obstacle] = &[];
+/// Image whose dark pixels block the cells under them, equirectangular on the spheres
+pub const OBSTACLE_MASK: Option<&str> = None;
+
+// Terrain
+pub const TERRAIN: Terrain = Terrain::Flat;
+/// Elevation of the highest cells
+pub const TERRAIN_HEIGHT: f32 = 0.5;
+pub const TERRAIN_MOVEMENT: TerrainMovement = TerrainMovement {
+    downhill_preference: 1.,
+    steep_slope: 1.,
+    steep_rejection: 0.5,
+};
 
 // Ant
 pub const ANT_COUNT: usize = 100;
 pub const ANT_RADIUS: f32 = 0.06;
 pub const ANT_HEIGHT: f32 = 0.2;
+pub const VISION_RADIUS: usize = 1;
+/// Items each ant carries at most
+pub const ANT_CAPACITY: usize = 1;
+/// Where the ants drop their items, the drop policy given on the command line replaces it
+pub const DROP_POLICY: DropPolicy = DropPolicy::OwnCell;
+pub const MOVEMENT_MODEL: MovementModel = MovementModel::Uniform;
+/// Moves made by each ant every step, rounded and at least 1
+pub const ANT_SPEED: ParameterDistribution = ParameterDistribution::Constant(1.);
+/// Drawn for every ant when it spawns, the same for every ant by default
+pub const ANT_PARAMETERS: AntParameterConfig = AntParameterConfig {
+    steepness: ParameterDistribution::Constant(1.),
+    vision_radius: None,
+    speed: ANT_SPEED,
+    laziness: ParameterDistribution::Constant(0.),
+};
+/// Cells with items remembered by each ant, disabled with a size of zero
+pub const ANT_MEMORY: MemoryConfig = MemoryConfig { size: 0, bias: 2. };
+
+// Colonies
+/// Ants spawned at startup, the movement model given on the command line replaces theirs
+pub const COLONIES: &[ColonyConfig] = &[ColonyConfig {
+    name: "black",
+    count: ANT_COUNT,
+    color: Color::BLACK,
+    vision_radius: VISION_RADIUS,
+    movement: MOVEMENT_MODEL,
+    probability: ProbabilityModel::Sine,
+    capacity: ANT_CAPACITY,
+    drop_policy: DROP_POLICY,
+    parameters: ANT_PARAMETERS,
+    kinds: None,
+}];
+
+// Lifecycle
+/// Energy model where ants die when exhausted and new ones are born, ants live forever when unset
+pub const LIFECYCLE: Option<LifecycleConfig> = None;
+
+// Crowding
+/// Exclusion and interaction rules between the ants, which ignore each other when unset
+pub const CROWDING: Option<CrowdingConfig> = None;
+
+// Disturbance
+/// Items added and removed during the run, only the first ones exist when unset
+pub const DISTURBANCE: Option<DisturbanceConfig> = None;
+
+// Pheromone
+/// Disabled when nothing is deposited
+pub const PHEROMONE: PheromoneConfig = PheromoneConfig {
+    drop_deposit: 0.,
+    carry_deposit: 0.,
+    diffusion: 0.1,
+    evaporation: 0.05,
+    attraction: 1.,
+    drop_boost: 0.,
+};
+
+// Foraging
+/// Nests and food sources of the nest and forage mode, replacing sorting when set
+pub const FORAGING: Option<ForagingConfig> = None;
+
+// Ant colony optimisation
+/// Shortest path or tour searched by ACO alongside the simulation when set
+pub const ACO: Option<AcoConfig> = None;
 
 // Food
 pub const ITEM_SUBDIVISIONS: usize = 2;
 pub const ITEM_RADIUS: f32 = 0.2;
-pub const ITEM_COUNT: usize = 1000;
\ No newline at end of file
+pub const ITEM_COUNT: usize = 1000;
+/// Items a cell holds at most, stacked on each other
+pub const STACK_HEIGHT: usize = 1;
+/// Items are spread evenly over this many kinds, each with its own colour
+pub const ITEM_KINDS: usize = 1;
\ No newline at end of file
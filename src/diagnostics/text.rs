@@ -1,52 +1,108 @@
 use bevy::diagnostic::{Diagnostics, FrameTimeDiagnosticsPlugin};
 use bevy::prelude::*;
+use std::collections::VecDeque;
 
+use crate::simulation::aco::{AcoSolver, Route};
+use crate::simulation::ant::AntParameters;
+use crate::simulation::lifecycle::POPULATION_SAMPLE_INTERVAL;
+use crate::simulation::state::SimulationState;
 use crate::timestep::diagnostic::TimeStepDiagnosticsPlugin;
 
 #[derive(Component)]
 pub struct DiagnosticsText;
 
+// Lines only filled with some features, each one a label section followed by a value section
+/// When foraging
+const FOOD_LINE: usize = 5;
+/// When running ACO
+const ACO_LINE: usize = 7;
+/// With several colonies
+const COLONIES_LINE: usize = 9;
+/// With the energy model
+const POPULATION_LINE: usize = 11;
+/// When items are added and removed
+const ITEMS_LINE: usize = 13;
+/// When ants crowd or interact
+const CROWDING_LINE: usize = 15;
+/// When the parameters of the ants vary
+const PARAMETERS_LINE: usize = 17;
+/// Number of optional lines, the last one being [`PARAMETERS_LINE`]
+const OPTIONAL_LINES: usize = 7;
+
+/// Empty label and value sections of an optional line
+fn line_sections(asset_server: &AssetServer) -> [TextSection; 2] {
+    let style = |color| TextStyle {
+        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+        font_size: 20.0,
+        color,
+    };
+    [
+        TextSection::from_style(style(Color::BLACK)),
+        TextSection::from_style(style(Color::BLUE)),
+    ]
+}
+
+/// Lowest and highest sampled population, with the steps the samples cover
+fn population_range(population: &VecDeque<usize>) -> String {
+    match (population.iter().min(), population.iter().max()) {
+        (Some(min), Some(max)) => format!(
+            ", {min} to {max} over the last {} steps",
+            population.len() as u64 * POPULATION_SAMPLE_INTERVAL
+        ),
+        _ => String::new(),
+    }
+}
+
+fn set_line(text: &mut Text, line: usize, label: &str, value: String) {
+    text.sections[line].value = label.to_string();
+    text.sections[line + 1].value = value;
+}
+
 pub fn diagnostics_text_setup(mut commands: Commands, asset_server: Res<AssetServer>) {
     commands
         .spawn()
         .insert(DiagnosticsText)
         .insert_bundle(TextBundle {
-            text: Text::from_sections([
-                TextSection::new(
-                    "FPS: ",
-                    TextStyle {
-                        font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                        font_size: 20.0,
-                        color: Color::BLACK,
-                    },
-                ),
-                TextSection::from_style(TextStyle {
-                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                    font_size: 20.0,
-                    color: Color::BLUE,
-                }),
-                TextSection::new(
-                    "\n",
-                    TextStyle {
+            text: Text::from_sections(
+                [
+                    TextSection::new(
+                        "FPS: ",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 20.0,
+                            color: Color::BLACK,
+                        },
+                    ),
+                    TextSection::from_style(TextStyle {
                         font: asset_server.load("fonts/FiraMono-Medium.ttf"),
                         font_size: 20.0,
-                        color: Color::BLACK,
-                    },
-                ),
-                TextSection::new(
-                    "UPS: ",
-                    TextStyle {
+                        color: Color::BLUE,
+                    }),
+                    TextSection::new(
+                        "\n",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 20.0,
+                            color: Color::BLACK,
+                        },
+                    ),
+                    TextSection::new(
+                        "UPS: ",
+                        TextStyle {
+                            font: asset_server.load("fonts/FiraMono-Medium.ttf"),
+                            font_size: 20.0,
+                            color: Color::BLACK,
+                        },
+                    ),
+                    TextSection::from_style(TextStyle {
                         font: asset_server.load("fonts/FiraMono-Medium.ttf"),
                         font_size: 20.0,
-                        color: Color::BLACK,
-                    },
-                ),
-                TextSection::from_style(TextStyle {
-                    font: asset_server.load("fonts/FiraMono-Medium.ttf"),
-                    font_size: 20.0,
-                    color: Color::BLUE,
-                }),
-            ]),
+                        color: Color::BLUE,
+                    }),
+                ]
+                .into_iter()
+                .chain((0..OPTIONAL_LINES).flat_map(|_| line_sections(&asset_server))),
+            ),
             style: Style {
                 align_self: AlignSelf::FlexEnd,
                 ..default()
@@ -60,6 +116,8 @@ pub fn diagnostics_text_setup(mut commands: Commands, asset_server: Res<AssetSer
 
 pub fn diagnostics_text_update(
     diagnostics: Res<Diagnostics>,
+    state: Res<SimulationState>,
+    aco: Option<Res<AcoSolver>>,
     mut query: Query<&mut Text, With<DiagnosticsText>>,
 ) {
     for mut text in &mut query {
@@ -73,6 +131,102 @@ pub fn diagnostics_text_update(
                 text.sections[4].value = format!("{average:.0}");
             }
         }
+        if let Some(foraging) = &state.foraging {
+            let rate = foraging.delivered as f64 / state.step_count.max(1) as f64;
+            set_line(
+                &mut text,
+                FOOD_LINE,
+                "\nFood: ",
+                format!(
+                    "{} ({} last step, {rate:.3}/step)",
+                    foraging.delivered, foraging.delivered_last_step
+                ),
+            );
+        }
+        if let Some(aco) = &aco {
+            let length = |route: &Option<Route>| match route {
+                Some((_, length)) => format!("{length:.3}"),
+                None => "-".to_string(),
+            };
+            set_line(
+                &mut text,
+                ACO_LINE,
+                "\nACO: ",
+                format!(
+                    "{} (reference {})",
+                    length(&aco.best),
+                    length(&aco.reference)
+                ),
+            );
+        }
+        if state.colonies.len() > 1 && state.is_changed() {
+            let colonies: String = state
+                .colonies
+                .iter()
+                .enumerate()
+                .map(|(i, colony)| {
+                    let (ants, carrying) = state.colony_ant_counts(i);
+                    format!(
+                        "\n {}: {:.3} density, {carrying}/{ants} carrying",
+                        colony.config.name,
+                        state.colony_item_density(i)
+                    )
+                })
+                .collect();
+            set_line(&mut text, COLONIES_LINE, "\nColonies:", colonies);
+        }
+        if let Some(lifecycle) = &state.lifecycle {
+            set_line(
+                &mut text,
+                POPULATION_LINE,
+                "\nPopulation: ",
+                format!(
+                    "{} ({} born, {} dead){}",
+                    state.population(),
+                    lifecycle.births,
+                    lifecycle.deaths,
+                    population_range(&lifecycle.population)
+                ),
+            );
+        }
+        if let Some(disturbance) = &state.disturbance {
+            set_line(
+                &mut text,
+                ITEMS_LINE,
+                "\nItems: ",
+                format!(
+                    "{} ({} added, {} decayed, {} removed)",
+                    state.items_on_board(),
+                    disturbance.added,
+                    disturbance.decayed,
+                    disturbance.removed
+                ),
+            );
+        }
+        if let Some(crowding) = &state.crowding {
+            set_line(
+                &mut text,
+                CROWDING_LINE,
+                "\nCrowding: ",
+                format!(
+                    "{} blocked, {} exchanges",
+                    crowding.blocked, crowding.exchanges
+                ),
+            );
+        }
+        if state.has_varying_parameters() && state.is_changed() {
+            let parameters: String = AntParameters::NAMES
+                .iter()
+                .zip(state.parameter_summaries())
+                .map(|(name, summary)| {
+                    format!(
+                        "\n {name}: {:.2} ± {:.2} ({:.2} to {:.2})",
+                        summary.mean, summary.std_dev, summary.min, summary.max
+                    )
+                })
+                .collect();
+            set_line(&mut text, PARAMETERS_LINE, "\nParameters:", parameters);
+        }
     }
 }
 
@@ -5,7 +5,6 @@ pub struct DebugInspectorPlugin;
 use crate::simulation::ant::Ant;
 // use crate::simulation::food::Food;
 use crate::timestep::fixed_timestep::FixedTimestepConfig;
-use crate::simulation::board::IcoBoard;
 
 #[allow(unused_imports)]
 use bevy_inspector_egui::{
@@ -23,11 +22,6 @@ pub struct AntInspector {
 //     foods: InspectorQuery<Entity, With<Food>>,
 // }
 
-#[derive(Default, Inspectable)]
-pub struct BoardInspector {
-    board: ResourceInspector<IcoBoard>,
-}
-
 #[derive(Default, Inspectable)]
 pub struct SimulationControlInspector {
     config: ResourceInspector<FixedTimestepConfig>,
@@ -40,7 +34,6 @@ impl Plugin for DebugInspectorPlugin {
             app
                 // .add_plugin(WorldInspectorPlugin::new())
                 // .add_plugin(InspectorPlugin::<AntInspector>::new())
-                // .add_plugin(InspectorPlugin::<BoardInspector>::new())
                 // .add_plugin(InspectorPlugin::<FoodInspector>::new())
                 // .add_plugin(InspectorPlugin::<SimulationControlInspector>::new())
             ;
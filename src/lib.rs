@@ -0,0 +1,6 @@
+pub mod camera;
+pub mod consts;
+pub mod diagnostics;
+pub mod inspector;
+pub mod simulation;
+pub mod timestep;
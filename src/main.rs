@@ -1,29 +1,39 @@
-mod camera;
-mod consts;
-mod diagnostics;
-mod inspector;
-mod simulation;
-mod timestep;
-
-use crate::camera::CameraPlugin;
+use ant::camera::CameraPlugin;
 use bevy::pbr::wireframe::WireframePlugin;
 use bevy::prelude::*;
 use bevy::render::settings::{WgpuFeatures, WgpuSettings};
 use bevy::window::PresentMode;
-use iyes_loopless::prelude::*;
 use std::time::Duration;
 
-use crate::consts::{STARTING_UPS, WINDOW_SIZE};
-use crate::diagnostics::SimulationDiagnosticsPlugin;
-use crate::inspector::DebugInspectorPlugin;
-use crate::simulation::ant::Ant;
-use crate::simulation::board::{BoardPosition, IcoBoard};
-use crate::simulation::control::SimulationStatus;
-use crate::timestep::fixed_timestep::{FixedTimestepConfig, FixedTimestepStage};
-use crate::timestep::FixedUpdateLabel;
+use ant::consts::{BOARD_TOPOLOGY, STARTING_UPS, WINDOW_SIZE};
+use ant::diagnostics::SimulationDiagnosticsPlugin;
+use ant::inspector::DebugInspectorPlugin;
+use ant::simulation;
+use ant::simulation::ant::{Ant, MovementModel};
+use ant::simulation::board::{BoardPosition, BoardRebuildEvent, BoardTopology};
+use ant::simulation::control::SimulationStatus;
+use ant::timestep;
+use ant::timestep::fixed_timestep::{FixedTimestepConfig, FixedTimestepStage};
+use ant::timestep::FixedUpdateLabel;
 
 fn main() {
-    App::new()
+    let topology = match std::env::args().nth(1) {
+        Some(arg) => arg
+            .parse::<BoardTopology>()
+            .unwrap_or_else(|e| panic!("{e}")),
+        None => BOARD_TOPOLOGY,
+    };
+    let movement = std::env::args().nth(2).map(|arg| {
+        arg.parse::<MovementModel>()
+            .unwrap_or_else(|e| panic!("{e}"))
+    });
+
+    let mut app = App::new();
+    // Replaces the movement model of every colony
+    if let Some(movement) = movement {
+        app.insert_resource(movement);
+    }
+    app
         // Resources
         .insert_resource(ClearColor(Color::WHITE))
         .insert_resource(WindowDescriptor {
@@ -39,6 +49,8 @@ fn main() {
             ..default()
         })
         .insert_resource(SimulationStatus::default())
+        .insert_resource(topology.default_resolution())
+        .insert_resource(topology)
         .insert_resource(FixedTimestepConfig::new(Duration::from_secs_f64(
             STARTING_UPS,
         )))
@@ -48,40 +60,45 @@ fn main() {
         .add_plugin(CameraPlugin)
         .add_plugin(DebugInspectorPlugin)
         .add_plugin(SimulationDiagnosticsPlugin)
+        .add_event::<BoardRebuildEvent>()
         // Register types
-        .register_type::<IcoBoard>()
         .register_type::<Ant>()
         .register_type::<BoardPosition>()
         // Simulation Stage
         .add_stage_before(
             CoreStage::Update,
             FixedUpdateLabel,
-            FixedTimestepStage::empty().with_stage(
-                SystemStage::parallel().with_system_set(
-                    ConditionSet::new()
-                        .run_if_not(simulation::control::is_simulation_paused)
-                        .with_system(simulation::ant::ant_move)
-                        .with_system(simulation::ant::ant_pickup_drop)
-                        .into(),
-                ),
-            ),
+            FixedTimestepStage::empty().with_stage(simulation::simulation_stage()),
         )
         // Setup
-        .add_startup_system_to_stage(StartupStage::PreStartup, simulation::board::icosphere_setup)
+        .add_startup_system_to_stage(StartupStage::PreStartup, simulation::board::board_setup)
         .add_startup_system(simulation::ant::ant_spawn)
-        .add_startup_system(simulation::item::item_spawn)
+        .add_startup_system(simulation::item::item_spawn.after(simulation::ant::ant_spawn))
+        .add_startup_system(simulation::foraging::nest_spawn)
+        .add_startup_system(simulation::aco::aco_setup)
         .add_startup_system(simulation::ant::draw_probability_function)
         // Per Frame Systems
-        .add_system(simulation::ant::ant_texture_update)
-        .add_system(simulation::ant::ant_position_update)
         .add_system(
-            simulation::item::item_pickup_update
-                .before(simulation::item::item_position_update),
+            simulation::ant::ant_sync
+                .before(simulation::ant::ant_texture_update)
+                .before(simulation::ant::ant_position_update),
         )
-        .add_system(simulation::item::item_position_update)
+        .add_system(simulation::ant::ant_texture_update)
+        .add_system(simulation::ant::ant_birth_spawn)
+        .add_system(simulation::ant::ant_position_update)
+        .add_system(simulation::item::item_added_spawn)
+        .add_system(simulation::item::item_sync)
+        .add_system(simulation::board::board_cell_color_update)
+        .add_system(simulation::board::obstacle_sync)
+        .add_system(simulation::board::obstacle_paint_input_handler)
+        .add_system(simulation::foraging::nest_sync)
+        .add_system(simulation::aco::aco_path_update)
         .add_system(simulation::control::simulation_pause_input_handler)
         .add_system(simulation::control::simulation_ending_input_handler)
         .add_system(simulation::control::wireframe_input_handler)
+        .add_system(simulation::control::board_resolution_input_handler)
+        // Rebuilt before the per frame systems, so they never see entities of the old board
+        .add_system_to_stage(CoreStage::PreUpdate, simulation::board::board_rebuild)
         .add_system(timestep::control::timestep_input_handler)
         // Run
         .run();
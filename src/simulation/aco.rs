@@ -0,0 +1,481 @@
+//! Ant colony optimisation on the board graph, finding the shortest path between two cells
+//! or the shortest closed tour through several, compared with exact or greedy answers
+
+use bevy::prelude::*;
+use bevy::render::mesh::PrimitiveTopology;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+use crate::consts::{ACO, SEED};
+use crate::simulation::board::{Board, BoardPosition};
+use crate::simulation::state::SimulationState;
+
+/// Colour of the best path or tour found
+pub const ACO_PATH_COLOR: Color = Color::BLUE;
+
+/// Height of the drawn path above the board, so it isn't hidden by the faces
+const ACO_PATH_ELEVATION: f32 = 0.05;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum AcoProblem {
+    /// Shortest path between the open cells closest to two points
+    Path { source: [f32; 3], target: [f32; 3] },
+    /// Shortest closed tour through the open cells closest to the points
+    Tour { stops: &'static [[f32; 3]] },
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AcoConfig {
+    pub problem: AcoProblem,
+    /// Ants sent every iteration
+    pub colony_size: usize,
+    /// Moves are picked with a probability proportional to `pheromone^alpha * heuristic^beta`,
+    /// the heuristic being the inverse distance to the target or to the next stop
+    pub alpha: f64,
+    pub beta: f64,
+    /// Share of the pheromone evaporating every iteration
+    pub evaporation: f64,
+    /// Pheromone laid by each ant on its way, divided by the length of the way
+    pub deposit: f64,
+    /// Extra ants laying pheromone on the best way found so far every iteration
+    pub elitist: f64,
+}
+
+/// Path through cells, with its length
+pub type Route = (Vec<BoardPosition>, f32);
+
+/// Runs the ACO iterations on a board
+#[derive(Debug, Clone)]
+pub struct AcoSolver {
+    pub config: AcoConfig,
+    /// Source and target of a path, or the stops of a tour
+    pub stops: Vec<BoardPosition>,
+    /// On every directed edge of the board, in the order of `BoardGraph::adj`, for paths.
+    /// On every pair of stops, `stops.len()` per row, for tours
+    pheromone: Vec<f64>,
+    /// Shortest path between every pair of stops of a tour, `stops.len()` per row
+    legs: Vec<Option<Route>>,
+    pub best: Option<Route>,
+    /// Cells of the best path or stops of the best tour, in the order ants walk them
+    best_way: Vec<usize>,
+    /// Shortest path found by Dijkstra, or the nearest neighbour tour
+    pub reference: Option<Route>,
+    pub iteration: usize,
+    rng: SmallRng,
+}
+
+impl AcoSolver {
+    pub fn new(config: AcoConfig, board: &dyn Board, seed: u64) -> Self {
+        let graph = board.graph();
+        let stop = |point: &[f32; 3]| {
+            let closest = graph.closest_cell(Vec3::from(*point), BoardPosition::default());
+            graph
+                .find_nearby(closest, |pos| !graph.is_blocked(pos))
+                .unwrap()
+        };
+
+        let mut solver = Self {
+            config,
+            stops: Vec::new(),
+            pheromone: Vec::new(),
+            legs: Vec::new(),
+            best: None,
+            best_way: Vec::new(),
+            reference: None,
+            iteration: 0,
+            rng: SmallRng::seed_from_u64(seed),
+        };
+        match config.problem {
+            AcoProblem::Path { source, target } => {
+                solver.stops = vec![stop(&source), stop(&target)];
+                if solver.stops[0] == solver.stops[1] {
+                    let error_message = "The ACO source and target are the same cell";
+                    error!("{error_message}");
+                    panic!("{error_message}");
+                }
+                solver.pheromone = vec![1.; graph.adj.len()];
+                solver.reference = shortest_path(board, solver.stops[0], solver.stops[1]);
+            }
+            AcoProblem::Tour { stops } => {
+                // Stops closest to the same cell are merged, a leg between them would be empty
+                for pos in stops.iter().map(stop) {
+                    if !solver.stops.contains(&pos) {
+                        solver.stops.push(pos);
+                    }
+                }
+                if solver.stops.len() < stops.len() {
+                    warn!(
+                        "{} ACO stops share a cell with another one and were merged",
+                        stops.len() - solver.stops.len()
+                    );
+                }
+                if solver.stops.len() < 2 {
+                    let error_message = "An ACO tour needs at least two stops on different cells";
+                    error!("{error_message}");
+                    panic!("{error_message}");
+                }
+                let n = solver.stops.len();
+                solver.pheromone = vec![1.; n * n];
+                solver.find_legs(board);
+            }
+        }
+        solver
+    }
+
+    /// Shortest paths between the stops of a tour, and the reference they give
+    fn find_legs(&mut self, board: &dyn Board) {
+        let n = self.stops.len();
+        self.legs = (0..n * n)
+            .map(|k| shortest_path(board, self.stops[k / n], self.stops[k % n]))
+            .collect();
+        self.reference = self.nearest_neighbour_tour();
+    }
+
+    /// Follows cells being blocked or opened: the reference and the legs of a tour are found again,
+    /// and the best way is forgotten when it crosses a blocked cell
+    pub fn update_obstacles(&mut self, board: &dyn Board) {
+        match self.config.problem {
+            AcoProblem::Path { .. } => {
+                self.reference = shortest_path(board, self.stops[0], self.stops[1]);
+            }
+            AcoProblem::Tour { .. } => self.find_legs(board),
+        }
+        let crosses_wall = |(cells, _): &Route| cells.iter().any(|pos| board.is_blocked(pos));
+        if matches!(&self.best, Some(best) if crosses_wall(best)) {
+            self.best = None;
+            self.best_way.clear();
+        }
+    }
+
+    /// Sends a colony, evaporates the pheromone and lets every ant lay pheromone on its way.
+    /// Returns whether a shorter way was found
+    pub fn iterate(&mut self, board: &dyn Board) -> bool {
+        let ways: Vec<(Vec<usize>, f32)> = (0..self.config.colony_size)
+            .filter_map(|_| match self.config.problem {
+                AcoProblem::Path { .. } => self.walk_path(board),
+                AcoProblem::Tour { .. } => self.walk_tour(),
+            })
+            .collect();
+
+        for pheromone in &mut self.pheromone {
+            *pheromone *= 1. - self.config.evaporation;
+        }
+        let mut improved = false;
+        for (way, length) in ways {
+            self.lay(board, &way, self.config.deposit / length as f64);
+            if !matches!(&self.best, Some((_, best)) if *best <= length) {
+                self.best = Some((self.cells(&way), length));
+                self.best_way = way;
+                improved = true;
+            }
+        }
+        if let Some((_, length)) = self.best {
+            let best_way = std::mem::take(&mut self.best_way);
+            let deposit = self.config.elitist * self.config.deposit / length as f64;
+            self.lay(board, &best_way, deposit);
+            self.best_way = best_way;
+        }
+        self.iteration += 1;
+        improved
+    }
+
+    /// Adds `deposit` to the pheromone on both directions of every step of `way`
+    fn lay(&mut self, board: &dyn Board, way: &[usize], deposit: f64) {
+        match self.config.problem {
+            AcoProblem::Path { .. } => {
+                for pair in way.windows(2) {
+                    self.pheromone[edge(board, pair[0], pair[1])] += deposit;
+                    self.pheromone[edge(board, pair[1], pair[0])] += deposit;
+                }
+            }
+            AcoProblem::Tour { .. } => {
+                let n = self.stops.len();
+                for (k, &from) in way.iter().enumerate() {
+                    let to = way[(k + 1) % way.len()];
+                    self.pheromone[from * n + to] += deposit;
+                    self.pheromone[to * n + from] += deposit;
+                }
+            }
+        }
+    }
+
+    /// Cells of a way, expanding the legs between the stops of a tour
+    fn cells(&self, way: &[usize]) -> Vec<BoardPosition> {
+        match self.config.problem {
+            AcoProblem::Path { .. } => way.iter().map(|&i| BoardPosition::new(i)).collect(),
+            AcoProblem::Tour { .. } => {
+                let n = self.stops.len();
+                let mut cells = vec![self.stops[way[0]]];
+                for (k, &from) in way.iter().enumerate() {
+                    let to = way[(k + 1) % way.len()];
+                    let (leg, _) = self.legs[from * n + to].as_ref().unwrap();
+                    cells.extend_from_slice(&leg[1..]);
+                }
+                cells
+            }
+        }
+    }
+
+    /// Walks from the source without coming back to a cell, `None` at a dead end
+    fn walk_path(&mut self, board: &dyn Board) -> Option<(Vec<usize>, f32)> {
+        let (source, target) = (self.stops[0], self.stops[1]);
+        let target_point = Vec3::from(board.world_position(&target));
+        let mut visited = vec![false; board.size()];
+        let mut way = vec![source.idx()];
+        let mut length = 0.;
+        visited[source.idx()] = true;
+
+        let mut pos = source;
+        while pos != target {
+            let offset = board.graph().adj_offsets[pos.idx()] as usize;
+            let weights: Vec<f64> = board
+                .adjacent_indices(&pos)
+                .iter()
+                .enumerate()
+                .map(|(k, &j)| {
+                    let adjacent = BoardPosition::new(j as usize);
+                    if visited[j as usize] || board.is_blocked(&adjacent) {
+                        return 0.;
+                    }
+                    let distance =
+                        target_point.distance(Vec3::from(board.world_position(&adjacent)));
+                    let heuristic = 1. / (distance as f64 + 1e-3);
+                    self.pheromone[offset + k].powf(self.config.alpha)
+                        * heuristic.powf(self.config.beta)
+                })
+                .collect();
+            let k = pick(&weights, &mut self.rng)?;
+            let next = BoardPosition::new(board.adjacent_indices(&pos)[k] as usize);
+            length += Vec3::from(board.offset(&pos, &next)).length();
+            visited[next.idx()] = true;
+            way.push(next.idx());
+            pos = next;
+        }
+        Some((way, length))
+    }
+
+    /// Visits every stop once starting from the first, `None` when a stop can't be reached
+    fn walk_tour(&mut self) -> Option<(Vec<usize>, f32)> {
+        let n = self.stops.len();
+        let mut visited = vec![false; n];
+        let mut way = vec![0];
+        let mut length = 0.;
+        visited[0] = true;
+
+        while way.len() < n {
+            let from = *way.last().unwrap();
+            let weights: Vec<f64> = (0..n)
+                .map(|to| match &self.legs[from * n + to] {
+                    Some((_, leg)) if !visited[to] && *leg > 0. => {
+                        self.pheromone[from * n + to].powf(self.config.alpha)
+                            * (1. / *leg as f64).powf(self.config.beta)
+                    }
+                    _ => 0.,
+                })
+                .collect();
+            let to = pick(&weights, &mut self.rng)?;
+            length += self.legs[from * n + to].as_ref().unwrap().1;
+            visited[to] = true;
+            way.push(to);
+        }
+        length += self.legs[way[n - 1] * n].as_ref()?.1;
+        Some((way, length))
+    }
+
+    /// Tour always going to the closest stop not visited yet
+    fn nearest_neighbour_tour(&self) -> Option<Route> {
+        let n = self.stops.len();
+        if n < 2 {
+            return None;
+        }
+        let mut visited = vec![false; n];
+        let mut way = vec![0];
+        let mut length = 0.;
+        visited[0] = true;
+        while way.len() < n {
+            let from = *way.last().unwrap();
+            let (to, leg) = (0..n)
+                .filter(|&to| !visited[to])
+                .filter_map(|to| self.legs[from * n + to].as_ref().map(|(_, leg)| (to, *leg)))
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))?;
+            length += leg;
+            visited[to] = true;
+            way.push(to);
+        }
+        length += self.legs[way[n - 1] * n].as_ref()?.1;
+        Some((self.cells(&way), length))
+    }
+}
+
+/// Index in `BoardGraph::adj` of the edge from `from` to `to`
+fn edge(board: &dyn Board, from: usize, to: usize) -> usize {
+    let pos = BoardPosition::new(from);
+    let k = board
+        .adjacent_indices(&pos)
+        .iter()
+        .position(|&j| j as usize == to)
+        .unwrap();
+    board.graph().adj_offsets[from] as usize + k
+}
+
+/// Index drawn with a probability proportional to its weight, `None` when they are all zero
+fn pick(weights: &[f64], rng: &mut SmallRng) -> Option<usize> {
+    let total: f64 = weights.iter().sum();
+    if total <= 0. {
+        return None;
+    }
+    let mut target = rng.gen_range(0.0..total);
+    for (i, weight) in weights.iter().enumerate() {
+        target -= weight;
+        if target < 0. {
+            return Some(i);
+        }
+    }
+    // Rounding errors
+    weights.iter().rposition(|&weight| weight > 0.)
+}
+
+/// Shortest path between two cells avoiding the blocked ones, by Dijkstra's algorithm
+pub fn shortest_path(
+    board: &dyn Board,
+    source: BoardPosition,
+    target: BoardPosition,
+) -> Option<Route> {
+    let mut distance = vec![f32::INFINITY; board.size()];
+    let mut previous = vec![usize::MAX; board.size()];
+    // Non negative floats order like their bits
+    let mut queue = BinaryHeap::from([Reverse((0f32.to_bits(), source.idx()))]);
+    distance[source.idx()] = 0.;
+
+    while let Some(Reverse((bits, i))) = queue.pop() {
+        if i == target.idx() {
+            break;
+        }
+        if f32::from_bits(bits) > distance[i] {
+            continue;
+        }
+        let pos = BoardPosition::new(i);
+        for adjacent in board.graph().get_all_adjacent(&pos) {
+            if board.is_blocked(&adjacent) {
+                continue;
+            }
+            let candidate = distance[i] + Vec3::from(board.offset(&pos, &adjacent)).length();
+            if candidate < distance[adjacent.idx()] {
+                distance[adjacent.idx()] = candidate;
+                previous[adjacent.idx()] = i;
+                queue.push(Reverse((candidate.to_bits(), adjacent.idx())));
+            }
+        }
+    }
+
+    if distance[target.idx()].is_infinite() {
+        return None;
+    }
+    let mut path = vec![target];
+    while path.last().unwrap().idx() != source.idx() {
+        path.push(BoardPosition::new(previous[path.last().unwrap().idx()]));
+    }
+    path.reverse();
+    Some((path, distance[target.idx()]))
+}
+
+/// Line drawing the best way found
+#[derive(Component)]
+pub struct AcoPath;
+
+/// Starts the solver when [`ACO`] is set
+pub fn aco_setup(
+    mut commands: Commands,
+    state: Res<SimulationState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let config = match ACO {
+        Some(config) => config,
+        None => return,
+    };
+    let solver = AcoSolver::new(config, state.board.as_ref(), SEED);
+    match &solver.reference {
+        Some((_, length)) => info!("ACO reference length {length:.3}"),
+        None => warn!("ACO stops can't all be reached"),
+    }
+    commands.insert_resource(solver);
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: meshes.add(path_mesh(state.board.as_ref(), &[])),
+            material: materials.add(StandardMaterial {
+                base_color: ACO_PATH_COLOR,
+                unlit: true,
+                ..default()
+            }),
+            ..default()
+        })
+        .insert(AcoPath);
+}
+
+/// Runs one ACO iteration per fixed step
+pub fn aco_step(solver: Option<ResMut<AcoSolver>>, state: Res<SimulationState>) {
+    let mut solver = match solver {
+        Some(solver) => solver,
+        None => return,
+    };
+    if solver.iterate(state.board.as_ref()) {
+        let (_, length) = solver.best.as_ref().unwrap();
+        match &solver.reference {
+            Some((_, reference)) => info!(
+                "ACO iteration {}: best length {length:.3}, {:.1}% above the reference",
+                solver.iteration,
+                (length / reference - 1.) * 100.
+            ),
+            None => info!(
+                "ACO iteration {}: best length {length:.3}",
+                solver.iteration
+            ),
+        }
+    }
+}
+
+/// Redraws the best way when it changes, the solver changing every step
+pub fn aco_path_update(
+    solver: Option<Res<AcoSolver>>,
+    state: Res<SimulationState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    query: Query<&Handle<Mesh>, With<AcoPath>>,
+    mut drawn: Local<Vec<BoardPosition>>,
+) {
+    let solver = match solver {
+        Some(solver) if solver.is_changed() => solver,
+        _ => return,
+    };
+    let cells = match &solver.best {
+        Some((cells, _)) => cells.as_slice(),
+        None => &[],
+    };
+    if cells == drawn.as_slice() {
+        return;
+    }
+    for mesh in &query {
+        *meshes.get_mut(mesh).unwrap() = path_mesh(state.board.as_ref(), cells);
+    }
+    *drawn = cells.to_vec();
+}
+
+fn path_mesh(board: &dyn Board, cells: &[BoardPosition]) -> Mesh {
+    let normals: Vec<[f32; 3]> = cells.iter().map(|pos| board.normal(pos)).collect();
+    let positions: Vec<[f32; 3]> = cells
+        .iter()
+        .zip(&normals)
+        .map(|(pos, &normal)| {
+            (Vec3::from(board.world_position(pos)) + Vec3::from(normal) * ACO_PATH_ELEVATION).into()
+        })
+        .collect();
+    let uvs = vec![[0., 0.]; cells.len()];
+
+    let mut mesh = Mesh::new(PrimitiveTopology::LineStrip);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+    mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+    mesh
+}
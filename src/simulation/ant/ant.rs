@@ -1,133 +1,205 @@
 use bevy::prelude::*;
-use rand::Rng;
 
-use crate::consts::{ANT_COUNT, ANT_HEIGHT, ANT_RADIUS, BOARD_RADIUS};
+use crate::consts::{ANT_HEIGHT, ANT_RADIUS};
+use crate::simulation::ant::AntParameters;
 use crate::simulation::board::BoardPosition;
-use crate::{IcoBoard, SimulationStatus};
-use super::prob::probability_function;
+use crate::simulation::control::SimulationStatus;
+use crate::simulation::state::SimulationState;
+use crate::timestep::fixed_timestep::FixedTimestepInfo;
 
-const TRANSLATION_MULTIPLIER: f32 = 1. + (ANT_HEIGHT + 2. * ANT_RADIUS) / (2. * BOARD_RADIUS);
+/// Distance from the board surface to the ant center, so the ant stands on the board
+const ANT_ELEVATION: f32 = ANT_HEIGHT / 2. + ANT_RADIUS;
 
+/// Rendered ant, mirroring [`SimulationState::ants`]`[id]`
 #[derive(Default, Component, Reflect)]
 #[reflect(Component)]
 pub struct Ant {
-    pub item: Option<Entity>,
+    pub id: usize,
+    pub colony: usize,
+    /// Number of items it carries
+    pub carried: usize,
+    /// Shown by the inspector along with the rest of the ant
+    pub parameters: AntParameters,
+}
+
+/// Mesh and colony materials of the ants, kept to draw the ants born during the run
+pub struct AntAssets {
+    mesh: Handle<Mesh>,
+    colony_materials: Vec<Handle<StandardMaterial>>,
 }
 
 pub fn ant_spawn(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
-    board: Res<IcoBoard>,
+    mut state: ResMut<SimulationState>,
 ) {
-    let ant_mesh = meshes.add(
-        shape::Capsule {
-            radius: ANT_RADIUS,
-            depth: ANT_HEIGHT,
-            latitudes: 4,
-            longitudes: 8,
-            ..default()
-        }
-        .into(),
-    );
-    let ant_material = materials.add(Color::BLACK.into());
-    for _ in 0..ANT_COUNT {
-        let pos = board.new_random_position();
-
-        commands
-            .spawn()
-            .insert_bundle(PbrBundle {
-                mesh: ant_mesh.clone(),
-                material: ant_material.clone(),
-                transform: Transform {
-                    translation: board.world_position(&pos).into(),
-                    rotation: Quat::from_rotation_arc(
-                        Vec3::Y,
-                        Vec3::from(board.world_position(&pos)).normalize(),
-                    ),
-                    ..default()
-                },
+    let assets = AntAssets {
+        mesh: meshes.add(
+            shape::Capsule {
+                radius: ANT_RADIUS,
+                depth: ANT_HEIGHT,
+                latitudes: 4,
+                longitudes: 8,
                 ..default()
-            })
-            .insert(Ant::default())
-            .insert(pos);
+            }
+            .into(),
+        ),
+        colony_materials: state
+            .colonies
+            .iter()
+            .map(|colony| materials.add(colony.config.color.into()))
+            .collect(),
+    };
+    for id in state.spawn_colonies() {
+        spawn_ant_entity(&mut commands, &assets, &state, id);
     }
+    commands.insert_resource(assets);
 }
 
-pub fn ant_move(
-    status: Res<SimulationStatus>,
-    mut query: Query<(&Ant, &mut BoardPosition)>,
-    board: Res<IcoBoard>,
+/// Gives an entity to the ants born past the last one, the slots of dead ants keep theirs
+pub fn ant_birth_spawn(
+    mut commands: Commands,
+    state: Res<SimulationState>,
+    assets: Res<AntAssets>,
+    query: Query<&Ant>,
 ) {
-    for (ant, mut pos) in &mut query {
-        if status.ending && ant.item.is_none() {
-            continue;
-        }
-        *pos = board.get_random_adjacent(&pos);
+    if !state.is_changed() {
+        return;
+    }
+    for id in query.iter().count()..state.ants.len() {
+        spawn_ant_entity(&mut commands, &assets, &state, id);
     }
 }
 
-pub fn ant_pickup_drop(
-    status: Res<SimulationStatus>,
-    mut commands: Commands,
-    mut query: Query<(&BoardPosition, &mut Ant)>,
-    mut board: ResMut<IcoBoard>,
+fn spawn_ant_entity(
+    commands: &mut Commands,
+    assets: &AntAssets,
+    state: &SimulationState,
+    id: usize,
 ) {
-    let mut rng = rand::thread_rng();
-    for (pos, mut ant) in &mut query {
-        let (empty_cells, food_cells) = {
-            let mut empty_cells = 0;
-            let mut food_cells = 0;
-            for lookup_pos in board.get_all_adjacent(pos) {
-                match board.get_cell(&lookup_pos).food {
-                    None => empty_cells += 1,
-                    Some(_) => food_cells += 1,
-                }
-            }
-            (empty_cells as f64, food_cells as f64)
-        };
-        let ratio = food_cells / (food_cells + empty_cells);
-        let prob = probability_function(ratio);
+    let pos = state.ants[id].position;
+    let colony = state.ants[id].colony;
+    commands
+        .spawn()
+        .insert_bundle(PbrBundle {
+            mesh: assets.mesh.clone(),
+            material: assets.colony_materials[colony].clone(),
+            transform: Transform {
+                translation: state.board.world_position(&pos).into(),
+                rotation: Quat::from_rotation_arc(Vec3::Y, state.board.normal(&pos).into()),
+                ..default()
+            },
+            ..default()
+        })
+        .insert(Ant {
+            id,
+            colony,
+            carried: 0,
+            parameters: state.ants[id].parameters,
+        })
+        .insert(pos);
+}
 
-        match (board.get_cell(pos).food, ant.item) {
-            (Some(item), None) => {
-                if !status.ending && rng.gen_bool(1. - prob) {
-                    commands.entity(item).remove::<BoardPosition>();
-                    ant.item = board.get_cell_mut(pos).food.take();
-                }
-            }
-            (None, Some(item)) => {
-                if rng.gen_bool(prob) {
-                    commands.entity(item).insert(*pos);
-                    board.get_cell_mut(pos).food = ant.item.take();
+/// Copies the ants of the simulation state into their entities, touching only what changed.
+/// Dead ants, and the entities left over when the board is rebuilt with fewer ants, are hidden
+pub fn ant_sync(
+    state: Res<SimulationState>,
+    mut query: Query<(&mut Ant, &mut BoardPosition, &mut Visibility)>,
+) {
+    if !state.is_changed() {
+        return;
+    }
+    for (mut ant, mut pos, mut visibility) in &mut query {
+        let ant_state = match state.ants.get(ant.id) {
+            Some(ant_state) if ant_state.alive => ant_state,
+            _ => {
+                if visibility.is_visible {
+                    visibility.is_visible = false;
                 }
+                continue;
             }
-            (_, _) => {}
+        };
+        if !visibility.is_visible {
+            visibility.is_visible = true;
+        }
+        if ant.colony != ant_state.colony {
+            ant.colony = ant_state.colony;
+        }
+        if *pos != ant_state.position {
+            *pos = ant_state.position;
+        }
+        if ant.carried != ant_state.items.len() {
+            ant.carried = ant_state.items.len();
+        }
+        // Ants born in the slot of a dead one have their own parameters
+        if ant.parameters != ant_state.parameters {
+            ant.parameters = ant_state.parameters;
         }
     }
 }
 
+/// Ants take the colour of their colony, turning crimson as they fill up to their capacity
 pub fn ant_texture_update(
     mut query: Query<(&mut Handle<StandardMaterial>, &Ant), Changed<Ant>>,
+    state: Res<SimulationState>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
     for (mut material, ant) in &mut query {
-        match ant.item {
-            Some(_) => *material = materials.add(Color::CRIMSON.into()),
-            None => *material = materials.add(Color::BLACK.into()),
-        }
+        let colony = &state.colonies[ant.colony].config;
+        let load = (ant.carried as f32 / colony.capacity as f32).min(1.);
+        let color = Vec4::from(colony.color.as_rgba_f32())
+            .lerp(Vec4::from(Color::CRIMSON.as_rgba_f32()), load);
+        *material = materials.add(Color::rgba(color.x, color.y, color.z, color.w).into());
     }
 }
 
+/// Walks every ant along the cells of its last step as the next step gets closer,
+/// so fast ants don't teleport. Jumps to cells further than a neighbour are not interpolated
 pub fn ant_position_update(
-    mut query: Query<(&mut Transform, &BoardPosition), (Changed<BoardPosition>, With<Ant>)>,
-    board: Res<IcoBoard>,
+    mut query: Query<(&mut Transform, &Ant)>,
+    state: Res<SimulationState>,
+    status: Res<SimulationStatus>,
+    timestep: Option<Res<FixedTimestepInfo>>,
 ) {
-    for (mut transform, pos) in &mut query {
-        let mut translation: Vec3 = board.world_position(pos).into();
-        translation *= TRANSLATION_MULTIPLIER;
-        transform.translation = translation;
-        transform.rotation =
-            Quat::from_rotation_arc(Vec3::Y, Vec3::from(board.world_position(&pos)).normalize());
+    let progress = match timestep {
+        Some(timestep) if !status.paused => timestep.overstep(),
+        _ => 1.,
+    };
+    let board = state.board.as_ref();
+    let cell = |pos: &BoardPosition| {
+        (
+            Vec3::from(board.world_position(pos)),
+            Vec3::from(board.normal(pos)),
+        )
+    };
+    for (mut transform, ant) in &mut query {
+        let ant_state = match state.ants.get(ant.id) {
+            Some(ant_state) => ant_state,
+            None => continue,
+        };
+        let (translation, normal) = match ant_state.path.as_slice() {
+            path if path.len() >= 2 => {
+                let segments = path.len() - 1;
+                let along = progress * segments as f32;
+                let segment = (along as usize).min(segments - 1);
+                let t = along - segment as f32;
+                let (from, to) = (&path[segment], &path[segment + 1]);
+                if board.adjacent_indices(from).contains(&(to.idx() as u32)) {
+                    let (start, start_normal) = cell(from);
+                    let (_, end_normal) = cell(to);
+                    let offset = Vec3::from(board.offset(from, to));
+                    (
+                        start + offset * t,
+                        start_normal.lerp(end_normal, t).normalize(),
+                    )
+                } else {
+                    cell(if t < 0.5 { from } else { to })
+                }
+            }
+            _ => cell(&ant_state.position),
+        };
+        transform.translation = translation + normal * ANT_ELEVATION;
+        transform.rotation = Quat::from_rotation_arc(Vec3::Y, normal);
     }
 }
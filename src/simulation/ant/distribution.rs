@@ -0,0 +1,51 @@
+use rand::{Rng, RngCore};
+use rand_distr::{Distribution, Normal};
+use std::ops::RangeInclusive;
+
+/// Distribution of a parameter of the ants, sampled once for each ant when it spawns
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ParameterDistribution {
+    Constant(f64),
+    /// Uniform between `min` and `max`, both included
+    Uniform {
+        min: f64,
+        max: f64,
+    },
+    Normal {
+        mean: f64,
+        std_dev: f64,
+    },
+}
+
+impl ParameterDistribution {
+    pub fn sample(&self, rng: &mut dyn RngCore) -> f64 {
+        match *self {
+            ParameterDistribution::Constant(value) => value,
+            ParameterDistribution::Uniform { min, max } if min < max => rng.gen_range(min..=max),
+            ParameterDistribution::Uniform { min, .. } => min,
+            ParameterDistribution::Normal { mean, std_dev } => Normal::new(mean, std_dev)
+                .unwrap_or_else(|e| panic!("Invalid normal distribution: {e}"))
+                .sample(rng),
+        }
+    }
+
+    /// Sample rounded to the nearest whole number, within [`ParameterDistribution::count_range`]
+    pub fn sample_count(&self, min: usize, rng: &mut dyn RngCore) -> usize {
+        let range = self.count_range(min);
+        (self.sample(rng).round().max(0.) as usize).clamp(*range.start(), *range.end())
+    }
+
+    /// Whole numbers [`ParameterDistribution::sample_count`] returns, at least `min`.
+    /// Normal samples are kept within three standard deviations of the mean
+    pub fn count_range(&self, min: usize) -> RangeInclusive<usize> {
+        let (low, high) = match *self {
+            ParameterDistribution::Constant(value) => (value, value),
+            ParameterDistribution::Uniform { min, max } => (min, max.max(min)),
+            ParameterDistribution::Normal { mean, std_dev } => {
+                (mean - 3. * std_dev.abs(), mean + 3. * std_dev.abs())
+            }
+        };
+        let count = |value: f64| (value.round().max(0.) as usize).max(min);
+        count(low)..=count(high)
+    }
+}
@@ -0,0 +1,49 @@
+use std::collections::VecDeque;
+
+use crate::simulation::board::BoardPosition;
+
+/// Short-term memory of Lumer and Faieta
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct MemoryConfig {
+    /// Number of cells remembered by each ant, zero disables the memory
+    pub size: usize,
+    /// Ants carrying an item favour the neighbours towards the densest remembered cell,
+    /// weighted by `exp(bias * cos(angle))`
+    pub bias: f64,
+}
+
+/// Last cells where an ant saw items, with the item density it saw there
+#[derive(Debug, Default, Clone)]
+pub struct Memory {
+    cells: VecDeque<(BoardPosition, f64)>,
+}
+
+impl Memory {
+    /// Remembers `pos`, forgetting the oldest cell beyond `size` cells
+    pub fn remember(&mut self, pos: BoardPosition, density: f64, size: usize) {
+        self.cells.retain(|(cell, _)| *cell != pos);
+        self.cells.push_back((pos, density));
+        while self.cells.len() > size {
+            self.cells.pop_front();
+        }
+    }
+
+    /// Remembered cell with the highest item density
+    pub fn densest(&self) -> Option<BoardPosition> {
+        self.cells
+            .iter()
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(pos, _)| *pos)
+    }
+
+    /// Remembers the cells `other` remembers, as if it had just seen them
+    pub fn learn(&mut self, other: &Memory, size: usize) {
+        for &(pos, density) in &other.cells {
+            self.remember(pos, density, size);
+        }
+    }
+
+    pub fn forget(&mut self) {
+        self.cells.clear();
+    }
+}
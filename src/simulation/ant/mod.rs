@@ -1,5 +1,13 @@
 mod ant;
+mod distribution;
+mod memory;
+mod movement;
+mod parameters;
 mod prob;
 
 pub use ant::*;
+pub use distribution::*;
+pub use memory::*;
+pub use movement::*;
+pub use parameters::*;
 pub use prob::*;
\ No newline at end of file
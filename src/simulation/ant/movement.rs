@@ -0,0 +1,211 @@
+use bevy::prelude::*;
+use rand::{Rng, RngCore};
+use std::str::FromStr;
+
+use crate::consts::TERRAIN_MOVEMENT;
+use crate::simulation::board::{Board, BoardPosition};
+
+/// How an ant picks the cells it walks to
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum MovementModel {
+    /// Uniformly random neighbour every step
+    Uniform,
+    /// Neighbours in the direction of the last move are favoured,
+    /// weighted by `exp(persistence * cos(turn))`
+    Correlated { persistence: f64 },
+    /// Straight flights in a random direction, `l` cells long with a probability proportional
+    /// to `l^-exponent`, `exponent` being between 1 and 3, up to `max_length` cells
+    Levy { exponent: f64, max_length: usize },
+    /// Uniform walk, jumping to a random cell of the board with probability `probability`
+    Jump { probability: f64 },
+}
+
+impl MovementModel {
+    /// Moves the ant at `pos`, pushing every cell it enters to `path`.
+    /// `heading` is the tangent direction of its last move, zero before the first one.
+    /// Single cell moves are weighted by `bias` too, flights ignore it.
+    /// The ant stays where it is when every way is blocked
+    pub fn walk(
+        &self,
+        board: &dyn Board,
+        pos: &mut BoardPosition,
+        heading: &mut Vec3,
+        path: &mut Vec<BoardPosition>,
+        bias: Option<&dyn Fn(&BoardPosition) -> f64>,
+        rng: &mut dyn RngCore,
+    ) {
+        let start = *pos;
+        match *self {
+            MovementModel::Uniform => *pos = biased_step(board, pos, bias, rng),
+            MovementModel::Correlated { persistence } => {
+                let current = *heading;
+                *pos = step(
+                    board,
+                    pos,
+                    &|adjacent| {
+                        let turn = current.dot(tangent_direction(board, &start, adjacent));
+                        let bias = bias.map_or(1., |bias| bias(adjacent));
+                        (persistence * turn as f64).exp() * bias
+                    },
+                    rng,
+                );
+                if *pos != start {
+                    *heading = transport(board, pos, tangent_direction(board, &start, pos));
+                }
+            }
+            MovementModel::Levy {
+                exponent,
+                max_length,
+            } => {
+                let length = levy_length(exponent, max_length, rng);
+                let first = board.get_random_adjacent(pos, rng);
+                let mut direction = tangent_direction(board, pos, &first);
+                for _ in 0..length {
+                    let straightest = board
+                        .adjacent_indices(pos)
+                        .iter()
+                        .map(|&i| BoardPosition::new(i as usize))
+                        .filter(|adjacent| !board.is_blocked(adjacent))
+                        .map(|adjacent| {
+                            let alignment = direction.dot(tangent_direction(board, pos, &adjacent));
+                            (adjacent, alignment)
+                        })
+                        .max_by(|(_, a), (_, b)| a.total_cmp(b));
+                    let next = match straightest {
+                        Some((next, alignment)) if alignment > 0. => next,
+                        _ => break,
+                    };
+                    if board.has_terrain() && TERRAIN_MOVEMENT.rejects(board, pos, &next, rng) {
+                        break;
+                    }
+                    direction = transport(board, &next, direction);
+                    *pos = next;
+                    path.push(next);
+                }
+                *heading = direction;
+                return;
+            }
+            MovementModel::Jump { probability } => {
+                *pos = if rng.gen_bool(probability) {
+                    board.new_random_position(rng)
+                } else {
+                    biased_step(board, pos, bias, rng)
+                };
+            }
+        }
+        if *pos != start {
+            path.push(*pos);
+        }
+    }
+}
+
+/// Moves the ant at `pos` one cell, favouring the neighbours in the direction of `target`,
+/// weighted by `exp(bias * cos(angle))`. Used instead of the [`MovementModel`]
+/// by ants carrying an item towards a remembered cell
+pub fn step_towards(
+    board: &dyn Board,
+    pos: &mut BoardPosition,
+    target: &BoardPosition,
+    bias: f64,
+    heading: &mut Vec3,
+    path: &mut Vec<BoardPosition>,
+    rng: &mut dyn RngCore,
+) {
+    let start = *pos;
+    let direction = tangent_direction(board, &start, target);
+    *pos = step(
+        board,
+        pos,
+        &|adjacent| (bias * direction.dot(tangent_direction(board, &start, adjacent)) as f64).exp(),
+        rng,
+    );
+    if *pos != start {
+        *heading = transport(board, pos, tangent_direction(board, &start, pos));
+        path.push(*pos);
+    }
+}
+
+impl FromStr for MovementModel {
+    type Err = String;
+
+    /// Parses the model name, with default parameters
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "uniform" => Ok(MovementModel::Uniform),
+            "correlated" => Ok(MovementModel::Correlated { persistence: 2. }),
+            "levy" => Ok(MovementModel::Levy {
+                exponent: 2.,
+                max_length: 20,
+            }),
+            "jump" => Ok(MovementModel::Jump { probability: 0.01 }),
+            _ => Err(format!(
+                "Unknown movement model {s}, expected one of uniform, correlated, levy or jump"
+            )),
+        }
+    }
+}
+
+/// Random open neighbour, biased by the slope when the board has a terrain
+fn uniform_step(board: &dyn Board, pos: &BoardPosition, rng: &mut dyn RngCore) -> BoardPosition {
+    if board.has_terrain() {
+        TERRAIN_MOVEMENT.get_adjacent(board, pos, rng)
+    } else {
+        board.get_random_adjacent(pos, rng)
+    }
+}
+
+/// Random open neighbour, weighted by `bias` when given
+fn biased_step(
+    board: &dyn Board,
+    pos: &BoardPosition,
+    bias: Option<&dyn Fn(&BoardPosition) -> f64>,
+    rng: &mut dyn RngCore,
+) -> BoardPosition {
+    match bias {
+        Some(bias) => step(board, pos, bias, rng),
+        None => uniform_step(board, pos, rng),
+    }
+}
+
+/// Open neighbour drawn with a probability proportional to `weight`, times the terrain weight
+/// when the board has a terrain, or `pos` itself when the terrain rejects the move
+fn step(
+    board: &dyn Board,
+    pos: &BoardPosition,
+    weight: &dyn Fn(&BoardPosition) -> f64,
+    rng: &mut dyn RngCore,
+) -> BoardPosition {
+    if !board.has_terrain() {
+        return board.get_weighted_adjacent(pos, weight, rng);
+    }
+    let next = board.get_weighted_adjacent(
+        pos,
+        &|adjacent| weight(adjacent) * TERRAIN_MOVEMENT.weight(board, pos, adjacent),
+        rng,
+    );
+    if TERRAIN_MOVEMENT.rejects(board, pos, &next, rng) {
+        *pos
+    } else {
+        next
+    }
+}
+
+/// Unit direction from `from` to `to` on the tangent plane at `from`
+fn tangent_direction(board: &dyn Board, from: &BoardPosition, to: &BoardPosition) -> Vec3 {
+    let normal = Vec3::from(board.normal(from));
+    let offset = Vec3::from(board.offset(from, to));
+    (offset - normal * offset.dot(normal)).normalize_or_zero()
+}
+
+/// `direction` carried over to the tangent plane at `pos`
+fn transport(board: &dyn Board, pos: &BoardPosition, direction: Vec3) -> Vec3 {
+    let normal = Vec3::from(board.normal(pos));
+    (direction - normal * direction.dot(normal)).normalize_or_zero()
+}
+
+/// Pareto distributed flight length, inverting its cumulative distribution
+fn levy_length(exponent: f64, max_length: usize, rng: &mut dyn RngCore) -> usize {
+    let u: f64 = rng.gen();
+    let length = (1. - u).powf(-1. / (exponent - 1.));
+    (length as usize).clamp(1, max_length)
+}
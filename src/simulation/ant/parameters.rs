@@ -0,0 +1,105 @@
+use bevy::prelude::*;
+use rand::RngCore;
+use std::ops::RangeInclusive;
+
+use crate::simulation::ant::ParameterDistribution;
+
+/// Distributions of the parameters of the ants of a colony
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct AntParameterConfig {
+    /// Scales the item density an ant sees before turning it into chances to pick up and drop,
+    /// higher values making the probability function steeper
+    pub steepness: ParameterDistribution,
+    /// Rounded and at least 1, the vision radius of the colony when `None`
+    pub vision_radius: Option<ParameterDistribution>,
+    /// Moves made every step, rounded and at least 1
+    pub speed: ParameterDistribution,
+    /// Chance of not acting at all in a step, clamped between 0 and 1
+    pub laziness: ParameterDistribution,
+}
+
+impl AntParameterConfig {
+    /// Draws the parameters of a new ant of a colony seeing `vision_radius` cells far
+    pub fn sample(&self, vision_radius: usize, rng: &mut dyn RngCore) -> AntParameters {
+        AntParameters {
+            steepness: self.steepness.sample(rng).max(0.),
+            vision_radius: self.vision_radius.map_or(vision_radius, |distribution| {
+                distribution.sample_count(1, rng)
+            }),
+            speed: self.speed.sample_count(1, rng),
+            laziness: self.laziness.sample(rng).clamp(0., 1.),
+        }
+    }
+
+    /// Vision radii the ants of a colony seeing `vision_radius` cells far may get
+    pub fn vision_radii(&self, vision_radius: usize) -> RangeInclusive<usize> {
+        self.vision_radius
+            .map_or(vision_radius..=vision_radius, |distribution| {
+                distribution.count_range(1)
+            })
+    }
+
+    /// Whether every ant gets the same parameters
+    pub fn is_constant(&self) -> bool {
+        [self.steepness, self.speed, self.laziness]
+            .iter()
+            .chain(&self.vision_radius)
+            .all(|distribution| matches!(distribution, ParameterDistribution::Constant(_)))
+    }
+}
+
+/// Parameters of a single ant, drawn when it spawns
+#[derive(Debug, Default, Copy, Clone, PartialEq, Reflect)]
+pub struct AntParameters {
+    pub steepness: f64,
+    pub vision_radius: usize,
+    pub speed: usize,
+    pub laziness: f64,
+}
+
+impl AntParameters {
+    pub const NAMES: [&'static str; 4] = ["steepness", "vision_radius", "speed", "laziness"];
+
+    /// Values of the parameters, in the order of [`AntParameters::NAMES`]
+    pub fn values(&self) -> [f64; 4] {
+        [
+            self.steepness,
+            self.vision_radius as f64,
+            self.speed as f64,
+            self.laziness,
+        ]
+    }
+}
+
+/// Spread of a parameter over the ants
+#[derive(Debug, Default, Copy, Clone, PartialEq)]
+pub struct ParameterSummary {
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl ParameterSummary {
+    pub fn new(values: impl Iterator<Item = f64>) -> Self {
+        let (mut count, mut sum, mut sum_squares) = (0, 0., 0.);
+        let (mut min, mut max) = (f64::INFINITY, f64::NEG_INFINITY);
+        for value in values {
+            count += 1;
+            sum += value;
+            sum_squares += value * value;
+            min = min.min(value);
+            max = max.max(value);
+        }
+        if count == 0 {
+            return Self::default();
+        }
+        let mean = sum / count as f64;
+        Self {
+            mean,
+            std_dev: (sum_squares / count as f64 - mean * mean).max(0.).sqrt(),
+            min,
+            max,
+        }
+    }
+}
@@ -4,8 +4,38 @@ use plotters::prelude::*;
 /// # parameters
 /// ratio = Food divided by total cells
 pub fn probability_function(ratio: f64) -> f64 {
-    let prob = (ratio * FRAC_PI_2).sin();
-    prob
+    (ratio * FRAC_PI_2).sin()
+}
+
+/// How the item density seen by an ant turns into its chances to pick up and drop items
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum ProbabilityModel {
+    /// [`probability_function`] to drop, its complement to pick up
+    Sine,
+    /// Deneubourg et al., `(k1 / (k1 + density))^2` to pick up and `(density / (k2 + density))^2` to drop
+    Threshold { k1: f64, k2: f64 },
+}
+
+impl ProbabilityModel {
+    pub fn pickup(&self, density: f64) -> f64 {
+        match *self {
+            ProbabilityModel::Sine => 1. - probability_function(density),
+            ProbabilityModel::Threshold { k1, .. } => (k1 / (k1 + density)).powi(2),
+        }
+    }
+
+    pub fn drop(&self, density: f64) -> f64 {
+        match *self {
+            ProbabilityModel::Sine => probability_function(density),
+            ProbabilityModel::Threshold { k2, .. } => {
+                if density > 0. {
+                    (density / (k2 + density)).powi(2)
+                } else {
+                    0.
+                }
+            }
+        }
+    }
 }
 
 pub fn draw_probability_function() {
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-#[derive(Default, Debug, Copy, Clone, Component, Reflect, Deref)]
+#[derive(Default, Debug, Copy, Clone, PartialEq, Eq, Component, Reflect, Deref)]
 #[reflect(Component)]
 pub struct BoardPosition(usize);
 
@@ -2,7 +2,12 @@ use bevy::prelude::*;
 use bevy::reflect::FromReflect;
 use bevy_inspector_egui::Inspectable;
 
-#[derive(Debug, Default, Copy, Clone, Reflect, FromReflect, Inspectable)]
+#[derive(Debug, Default, Clone, Reflect, FromReflect, Inspectable)]
 pub struct Cell {
-    pub food: Option<Entity>,
+    /// Indices of the items stacked on the cell, bottom first
+    pub food: Vec<usize>,
+    /// Kinds of the stacked items, in the same order
+    pub kinds: Vec<usize>,
+    /// Walls and holes, ants can't walk on them and items can't be dropped there
+    pub blocked: bool,
 }
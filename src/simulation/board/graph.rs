@@ -0,0 +1,739 @@
+use bevy::prelude::*;
+use bevy::reflect::FromReflect;
+use bevy_inspector_egui::Inspectable;
+use rand::prelude::SliceRandom;
+use rand::{Rng, RngCore};
+use std::cell::RefCell;
+use std::ops::Range;
+
+use crate::consts::{ITEM_KINDS, STACK_HEIGHT};
+use crate::simulation::board::{BoardPosition, Cell};
+
+/// Cells within some radius of every cell, with the items and walls among them kept up to date
+#[derive(Debug, Default, Clone, Reflect, FromReflect, Inspectable)]
+pub struct Vision {
+    radius: usize,
+    /// Cells within the radius of cell `i`, excluding itself, are `cells[offsets[i]..offsets[i + 1]]`
+    offsets: Vec<u32>,
+    cells: Vec<u32>,
+    /// Number of items, summing the stacks, within the radius of each cell
+    items: Vec<u32>,
+    /// Number of items of kind `k` within the radius of cell `i` at `kind_items[k * size + i]`,
+    /// only kept when there are several kinds
+    kind_items: Vec<u32>,
+    /// Number of blocked cells within the radius of each cell
+    blocked: Vec<u32>,
+}
+
+impl Vision {
+    /// Breadth first search from every cell up to `radius` edges,
+    /// counting the items and walls already on the board
+    fn new(graph: &BoardGraph, radius: usize) -> Self {
+        let size = graph.size();
+        let mut offsets = Vec::with_capacity(size + 1);
+        let mut cells = Vec::new();
+        let mut seen_from = vec![usize::MAX; size];
+        let mut frontier = Vec::new();
+        let mut next = Vec::new();
+        offsets.push(0);
+        for source in 0..size {
+            seen_from[source] = source;
+            frontier.push(source as u32);
+            for _ in 0..radius {
+                for cell in frontier.drain(..) {
+                    for &adjacent in graph.adjacent_indices(&BoardPosition::new(cell as usize)) {
+                        if seen_from[adjacent as usize] != source {
+                            seen_from[adjacent as usize] = source;
+                            cells.push(adjacent);
+                            next.push(adjacent);
+                        }
+                    }
+                }
+                std::mem::swap(&mut frontier, &mut next);
+            }
+            frontier.clear();
+            offsets.push(cells.len() as u32);
+        }
+
+        let mut vision = Self {
+            radius,
+            offsets,
+            cells,
+            items: vec![0; size],
+            kind_items: if graph.item_kinds > 1 {
+                vec![0; graph.item_kinds * size]
+            } else {
+                Vec::new()
+            },
+            blocked: vec![0; size],
+        };
+        for (idx, cell) in graph.cells.iter().enumerate() {
+            for &kind in &cell.kinds {
+                vision.count_item(idx, kind, true);
+            }
+            if cell.blocked {
+                vision.count_blocked(idx, true);
+            }
+        }
+        vision
+    }
+
+    fn range(&self, idx: usize) -> Range<usize> {
+        self.offsets[idx] as usize..self.offsets[idx + 1] as usize
+    }
+
+    /// Counts an item of `kind` placed on the cell `idx`, or taken off it,
+    /// in the cells seeing it, which are the ones it sees
+    fn count_item(&mut self, idx: usize, kind: usize, placed: bool) {
+        let size = self.items.len();
+        let by_kind = !self.kind_items.is_empty();
+        for i in self.range(idx) {
+            let cell = self.cells[i] as usize;
+            if placed {
+                self.items[cell] += 1;
+                if by_kind {
+                    self.kind_items[kind * size + cell] += 1;
+                }
+            } else {
+                self.items[cell] -= 1;
+                if by_kind {
+                    self.kind_items[kind * size + cell] -= 1;
+                }
+            }
+        }
+    }
+
+    fn count_blocked(&mut self, idx: usize, blocked: bool) {
+        for i in self.range(idx) {
+            let cell = self.cells[i] as usize;
+            if blocked {
+                self.blocked[cell] += 1;
+            } else {
+                self.blocked[cell] -= 1;
+            }
+        }
+    }
+
+    /// Number of open cells within the radius of the cell `idx`, excluding itself
+    fn open_count(&self, idx: usize) -> usize {
+        self.range(idx).len() - self.blocked[idx] as usize
+    }
+
+    /// Number of items of `kinds`, or of every kind when `None`, within the radius of the cell `idx`
+    fn item_count(&self, idx: usize, kinds: Option<&[usize]>) -> usize {
+        let size = self.items.len();
+        match kinds {
+            None => self.items[idx] as usize,
+            // A single kind, the kind 0
+            Some(kinds) if self.kind_items.is_empty() => {
+                if kinds.contains(&0) {
+                    self.items[idx] as usize
+                } else {
+                    0
+                }
+            }
+            Some(kinds) => kinds
+                .iter()
+                .filter_map(|kind| self.kind_items.get(kind * size + idx))
+                .map(|&count| count as usize)
+                .sum(),
+        }
+    }
+}
+
+/// Buffers of the searches of [`BoardGraph::item_density_within`] for radii without a vision,
+/// reused by every search of a thread
+#[derive(Default)]
+struct Search {
+    /// Cells reached by the search, marked with its stamp
+    stamps: Vec<u32>,
+    stamp: u32,
+    cells: Vec<u32>,
+}
+
+thread_local! {
+    static SEARCH: RefCell<Search> = RefCell::default();
+}
+
+/// Cells and their adjacency, shared by every board topology
+#[derive(Debug, Default, Clone, Reflect, Inspectable)]
+pub struct BoardGraph {
+    /// Neighbours of cell `i` are `adj[adj_offsets[i]..adj_offsets[i + 1]]`
+    pub adj_offsets: Vec<u32>,
+    pub adj: Vec<u32>,
+    pub vertex: Vec<[f32; 3]>,
+    /// The vision of the board first, then the ones added for ants seeing another radius
+    visions: Vec<Vision>,
+    cells: Vec<Cell>,
+    /// One bit per cell, set when the cell holds an item
+    occupied: Vec<u64>,
+    /// Items a cell holds at most
+    stack_height: usize,
+    /// Kinds of items, counted apart in the visions when there are several
+    item_kinds: usize,
+    blocked_count: usize,
+    /// Height of each cell above the board, empty on flat boards
+    elevation: Vec<f32>,
+}
+
+impl BoardGraph {
+    /// Builds the graph of cells placed at `vertex`, where the `i`-th element of `adjacency`
+    /// lists the neighbours of cell `i`, with cells seeing the items up to `vision_radius` edges away
+    pub fn new<I>(
+        vertex: Vec<[f32; 3]>,
+        adjacency: impl IntoIterator<Item = I>,
+        vision_radius: usize,
+    ) -> Self
+    where
+        I: IntoIterator<Item = u32>,
+    {
+        let mut adj_offsets = Vec::with_capacity(vertex.len() + 1);
+        let mut adj = Vec::with_capacity(vertex.len() * 6);
+        adj_offsets.push(0);
+        for neighbours in adjacency {
+            adj.extend(neighbours);
+            adj_offsets.push(adj.len() as u32);
+        }
+        assert_eq!(
+            adj_offsets.len(),
+            vertex.len() + 1,
+            "Adjacency of every cell is needed"
+        );
+
+        let cells = vec![Cell::default(); vertex.len()];
+        let occupied = vec![0; vertex.len() / 64 + 1];
+
+        let mut graph = Self {
+            adj_offsets,
+            adj,
+            vertex,
+            visions: Vec::new(),
+            cells,
+            occupied,
+            stack_height: STACK_HEIGHT,
+            item_kinds: ITEM_KINDS,
+            blocked_count: 0,
+            elevation: Vec::new(),
+        };
+        graph.add_vision(vision_radius);
+        graph
+    }
+
+    /// Precomputes what the cells see up to `radius` edges, for the ants seeing that far
+    pub fn add_vision(&mut self, radius: usize) {
+        if !self.visions.iter().any(|vision| vision.radius == radius) {
+            let vision = Vision::new(self, radius);
+            self.visions.push(vision);
+        }
+    }
+
+    /// Random cell which isn't blocked
+    pub fn new_random_position(&self, rng: &mut dyn RngCore) -> BoardPosition {
+        assert!(self.blocked_count < self.size(), "Every cell is blocked");
+        loop {
+            let pos = BoardPosition::new(rng.gen_range(0..self.size()));
+            if !self.is_blocked(&pos) {
+                break pos;
+            }
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.cells.len()
+    }
+
+    pub fn get_cell(&self, pos: &BoardPosition) -> &Cell {
+        &self.cells[pos.idx()]
+    }
+
+    /// Puts an item of `kind` on top of the stack of a cell which isn't full
+    pub fn place_item(&mut self, pos: &BoardPosition, item: usize, kind: usize) {
+        let idx = pos.idx();
+        debug_assert!(!self.is_full(pos), "Cell {idx} is full");
+        debug_assert!(!self.cells[idx].blocked, "Cell {idx} is blocked");
+        debug_assert!(kind < self.item_kinds, "Unknown item kind {kind}");
+        self.cells[idx].food.push(item);
+        self.cells[idx].kinds.push(kind);
+        self.occupied[idx / 64] |= 1u64 << (idx % 64);
+        for vision in &mut self.visions {
+            vision.count_item(idx, kind, true);
+        }
+    }
+
+    /// Removes the item on top of the stack of a cell, if any
+    pub fn take_item(&mut self, pos: &BoardPosition) -> Option<usize> {
+        let idx = pos.idx();
+        let item = self.cells[idx].food.pop();
+        if let Some(kind) = self.cells[idx].kinds.pop() {
+            self.item_removed(idx, kind);
+        }
+        item
+    }
+
+    /// Removes `item` from the stack of a cell wherever it lies, returning whether it was there
+    pub fn remove_item(&mut self, pos: &BoardPosition, item: usize) -> bool {
+        let idx = pos.idx();
+        let cell = &mut self.cells[idx];
+        match cell.food.iter().position(|&other| other == item) {
+            Some(level) => {
+                cell.food.remove(level);
+                let kind = cell.kinds.remove(level);
+                self.item_removed(idx, kind);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn item_removed(&mut self, idx: usize, kind: usize) {
+        if self.cells[idx].food.is_empty() {
+            self.occupied[idx / 64] &= !(1u64 << (idx % 64));
+        }
+        for vision in &mut self.visions {
+            vision.count_item(idx, kind, false);
+        }
+    }
+
+    /// Whether the cell holds at least one item
+    pub fn is_occupied(&self, pos: &BoardPosition) -> bool {
+        let idx = pos.idx();
+        self.occupied[idx / 64] & (1u64 << (idx % 64)) != 0
+    }
+
+    /// Whether the stack of the cell has reached the stack height
+    pub fn is_full(&self, pos: &BoardPosition) -> bool {
+        self.cells[pos.idx()].food.len() >= self.stack_height
+    }
+
+    /// Items the open cells can still take
+    pub fn free_slots(&self) -> usize {
+        self.cells
+            .iter()
+            .filter(|cell| !cell.blocked)
+            .map(|cell| self.stack_height.saturating_sub(cell.food.len()))
+            .sum()
+    }
+
+    pub fn is_blocked(&self, pos: &BoardPosition) -> bool {
+        self.cells[pos.idx()].blocked
+    }
+
+    /// Cells which aren't blocked
+    pub fn open_count(&self) -> usize {
+        self.size() - self.blocked_count
+    }
+
+    /// Turns an empty cell into a wall, or opens it again
+    pub fn set_blocked(&mut self, pos: &BoardPosition, blocked: bool) {
+        let idx = pos.idx();
+        if self.cells[idx].blocked == blocked {
+            return;
+        }
+        debug_assert!(self.cells[idx].food.is_empty(), "Cell {idx} has an item");
+        self.cells[idx].blocked = blocked;
+        for vision in &mut self.visions {
+            vision.count_blocked(idx, blocked);
+        }
+        if blocked {
+            self.blocked_count += 1;
+        } else {
+            self.blocked_count -= 1;
+        }
+    }
+
+    pub fn has_terrain(&self) -> bool {
+        !self.elevation.is_empty()
+    }
+
+    pub fn elevation(&self, pos: &BoardPosition) -> f32 {
+        self.elevation.get(pos.idx()).copied().unwrap_or(0.)
+    }
+
+    /// Stores the elevation of every cell, also moving the cells along `normals` when given
+    pub fn set_elevation(&mut self, elevation: Vec<f32>, normals: Option<&[[f32; 3]]>) {
+        assert_eq!(
+            elevation.len(),
+            self.size(),
+            "Elevation of every cell is needed"
+        );
+        if let Some(normals) = normals {
+            for (i, vertex) in self.vertex.iter_mut().enumerate() {
+                let previous = self.elevation.get(i).copied().unwrap_or(0.);
+                let offset = Vec3::from(normals[i]) * (elevation[i] - previous);
+                *vertex = (Vec3::from(*vertex) + offset).into();
+            }
+        }
+        self.elevation = elevation;
+    }
+
+    pub fn world_position(&self, pos: &BoardPosition) -> [f32; 3] {
+        self.vertex[pos.idx()]
+    }
+
+    /// Raw indices of the cells adjacent to `pos`
+    pub fn adjacent_indices(&self, pos: &BoardPosition) -> &[u32] {
+        let idx = pos.idx();
+        &self.adj[self.adj_offsets[idx] as usize..self.adj_offsets[idx + 1] as usize]
+    }
+
+    pub fn get_all_adjacent(
+        &self,
+        pos: &BoardPosition,
+    ) -> impl Iterator<Item = BoardPosition> + '_ {
+        self.adjacent_indices(pos)
+            .iter()
+            .map(|i| BoardPosition::new(*i as usize))
+    }
+
+    /// Number of open cells within the vision radius of `pos`, excluding itself
+    pub fn vision_count(&self, pos: &BoardPosition) -> usize {
+        self.visions[0].open_count(pos.idx())
+    }
+
+    /// Number of items within the vision radius of `pos`, summing the stacks
+    pub fn items_in_vision(&self, pos: &BoardPosition) -> usize {
+        self.visions[0].item_count(pos.idx(), None)
+    }
+
+    /// Ratio of the room of the open cells up to `radius` edges from `pos` taken by the items
+    /// of `kinds`, or of every kind when `None`. Radii without a vision added beforehand
+    /// are searched around `pos`, which is slower
+    pub fn item_density_within(
+        &self,
+        pos: &BoardPosition,
+        radius: usize,
+        kinds: Option<&[usize]>,
+    ) -> f64 {
+        let (items, open) = match self.visions.iter().find(|vision| vision.radius == radius) {
+            Some(vision) => (
+                vision.item_count(pos.idx(), kinds),
+                vision.open_count(pos.idx()),
+            ),
+            None => self.search_vision(pos, radius, kinds),
+        };
+        match open {
+            0 => 0.,
+            open => items as f64 / (open * self.stack_height) as f64,
+        }
+    }
+
+    /// Number of items of `kinds` and of open cells up to `radius` edges from `pos`, excluding it
+    fn search_vision(
+        &self,
+        pos: &BoardPosition,
+        radius: usize,
+        kinds: Option<&[usize]>,
+    ) -> (usize, usize) {
+        SEARCH.with(|search| {
+            let Search {
+                stamps,
+                stamp,
+                cells,
+            } = &mut *search.borrow_mut();
+            if stamps.len() < self.size() {
+                stamps.resize(self.size(), 0);
+            }
+            *stamp = stamp.wrapping_add(1);
+            if *stamp == 0 {
+                stamps.fill(0);
+                *stamp = 1;
+            }
+
+            cells.clear();
+            cells.push(pos.idx() as u32);
+            stamps[pos.idx()] = *stamp;
+            let mut frontier = 0..1;
+            for _ in 0..radius {
+                let start = cells.len();
+                for i in frontier {
+                    for &adjacent in self.adjacent_indices(&BoardPosition::new(cells[i] as usize)) {
+                        if stamps[adjacent as usize] != *stamp {
+                            stamps[adjacent as usize] = *stamp;
+                            cells.push(adjacent);
+                        }
+                    }
+                }
+                frontier = start..cells.len();
+            }
+
+            let (mut items, mut open) = (0, 0);
+            for &cell in &cells[1..] {
+                let cell = &self.cells[cell as usize];
+                if !cell.blocked {
+                    open += 1;
+                    items += match kinds {
+                        Some(kinds) => cell
+                            .kinds
+                            .iter()
+                            .filter(|kind| kinds.contains(kind))
+                            .count(),
+                        None => cell.food.len(),
+                    };
+                }
+            }
+            (items, open)
+        })
+    }
+
+    /// Ratio of the room of the open cells within the vision radius of `pos` taken by items,
+    /// each cell holding up to the stack height of them.
+    /// Zero when walls hide every cell around
+    pub fn item_density(&self, pos: &BoardPosition) -> f64 {
+        match self.vision_count(pos) {
+            0 => 0.,
+            seen => self.items_in_vision(pos) as f64 / (seen * self.stack_height) as f64,
+        }
+    }
+
+    /// Cell under the ray closest to `origin`.
+    /// The first cell the ray passes over, closer than 0.6 times the distance to its nearest
+    /// neighbour, is refined by walking to the neighbours closer to the ray
+    pub fn ray_cast(&self, origin: Vec3, direction: Vec3) -> Option<BoardPosition> {
+        let distance_to_ray = |pos: &BoardPosition| {
+            let point = Vec3::from(self.world_position(pos)) - origin;
+            (point - direction * point.dot(direction)).length()
+        };
+
+        let mut hit = None;
+        let mut hit_distance = f32::INFINITY;
+        for i in 0..self.size() {
+            let pos = BoardPosition::new(i);
+            let point = Vec3::from(self.vertex[i]);
+            let distance = (point - origin).dot(direction);
+            if distance < 0. || distance >= hit_distance {
+                continue;
+            }
+            let reach = self
+                .get_all_adjacent(&pos)
+                .map(|adjacent| point.distance(Vec3::from(self.world_position(&adjacent))))
+                .fold(f32::INFINITY, f32::min)
+                * 0.6;
+            if distance_to_ray(&pos) < reach {
+                hit = Some(pos);
+                hit_distance = distance;
+            }
+        }
+
+        let mut pos = hit?;
+        while let Some(closer) = self
+            .get_all_adjacent(&pos)
+            .filter(|adjacent| distance_to_ray(adjacent) < distance_to_ray(&pos))
+            .min_by(|a, b| distance_to_ray(a).total_cmp(&distance_to_ray(b)))
+        {
+            pos = closer;
+        }
+        Some(pos)
+    }
+
+    /// Cell closest to `point`, walking from `start` to the closest neighbour until none is closer.
+    /// Starting near the answer keeps repeated queries fast
+    pub fn closest_cell(&self, point: Vec3, start: BoardPosition) -> BoardPosition {
+        let distance = |pos: &BoardPosition| point.distance(Vec3::from(self.world_position(pos)));
+        let mut pos = start;
+        while let Some(closer) = self
+            .get_all_adjacent(&pos)
+            .filter(|adjacent| distance(adjacent) < distance(&pos))
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+        {
+            pos = closer;
+        }
+        pos
+    }
+
+    /// Closest cell to `start`, in edges, satisfying `predicate`
+    pub fn find_nearby(
+        &self,
+        start: BoardPosition,
+        predicate: impl Fn(&BoardPosition) -> bool,
+    ) -> Option<BoardPosition> {
+        let mut seen = vec![false; self.size()];
+        let mut queue = std::collections::VecDeque::from([start]);
+        seen[start.idx()] = true;
+        while let Some(pos) = queue.pop_front() {
+            if predicate(&pos) {
+                return Some(pos);
+            }
+            for adjacent in self.get_all_adjacent(&pos) {
+                if !seen[adjacent.idx()] {
+                    seen[adjacent.idx()] = true;
+                    queue.push_back(adjacent);
+                }
+            }
+        }
+        None
+    }
+
+    /// Random open neighbour with a probability proportional to `weight`,
+    /// or `pos` itself when walled in or when every weight is zero
+    pub fn get_weighted_adjacent(
+        &self,
+        pos: &BoardPosition,
+        weight: &dyn Fn(&BoardPosition) -> f64,
+        rng: &mut dyn RngCore,
+    ) -> BoardPosition {
+        let weight = |adjacent: &BoardPosition| {
+            if self.is_blocked(adjacent) {
+                0.
+            } else {
+                weight(adjacent)
+            }
+        };
+        let total: f64 = self.get_all_adjacent(pos).map(|a| weight(&a)).sum();
+        if total <= 0. {
+            return *pos;
+        }
+        let mut target = rng.gen_range(0.0..total);
+        for adjacent in self.get_all_adjacent(pos) {
+            target -= weight(&adjacent);
+            if target < 0. {
+                return adjacent;
+            }
+        }
+        // Rounding errors
+        self.get_all_adjacent(pos)
+            .filter(|a| weight(a) > 0.)
+            .last()
+            .unwrap()
+    }
+
+    /// Random open neighbour, or `pos` itself when walled in or without neighbours
+    pub fn get_random_adjacent(&self, pos: &BoardPosition, rng: &mut dyn RngCore) -> BoardPosition {
+        let adjacent = self.adjacent_indices(pos);
+        if self.blocked_count == 0 {
+            return adjacent
+                .choose(rng)
+                .map_or(*pos, |idx| BoardPosition::new(*idx as usize));
+        }
+
+        let is_open = |idx: &&u32| !self.cells[**idx as usize].blocked;
+        let open = adjacent.iter().filter(is_open).count();
+        if open == 0 {
+            return *pos;
+        }
+        let idx = adjacent
+            .iter()
+            .filter(is_open)
+            .nth(rng.gen_range(0..open))
+            .unwrap();
+        BoardPosition::new(*idx as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::SmallRng;
+    use rand::SeedableRng;
+
+    const SIDE: usize = 8;
+
+    /// Toroidal square grid, each cell with its four neighbours
+    fn torus(vision_radius: usize) -> BoardGraph {
+        let vertex = (0..SIDE * SIDE)
+            .map(|i| [(i % SIDE) as f32, (i / SIDE) as f32, 0.])
+            .collect();
+        let adjacency = (0..SIDE * SIDE).map(|i| {
+            let (x, y) = (i % SIDE, i / SIDE);
+            [
+                ((x + 1) % SIDE, y),
+                ((x + SIDE - 1) % SIDE, y),
+                (x, (y + 1) % SIDE),
+                (x, (y + SIDE - 1) % SIDE),
+            ]
+            .map(|(x, y)| (y * SIDE + x) as u32)
+        });
+        BoardGraph::new(vertex, adjacency, vision_radius)
+    }
+
+    /// Open cells, items and items of each kind up to `radius` edges from `idx`, excluding itself
+    fn recount(graph: &BoardGraph, idx: usize, radius: usize) -> (usize, usize, Vec<usize>) {
+        let mut seen = vec![idx];
+        let mut frontier = vec![idx];
+        for _ in 0..radius {
+            let mut next = Vec::new();
+            for cell in frontier {
+                for &adjacent in graph.adjacent_indices(&BoardPosition::new(cell)) {
+                    if !seen.contains(&(adjacent as usize)) {
+                        seen.push(adjacent as usize);
+                        next.push(adjacent as usize);
+                    }
+                }
+            }
+            frontier = next;
+        }
+        let (mut open, mut items, mut kinds) = (0, 0, vec![0; ITEM_KINDS]);
+        for &cell in &seen[1..] {
+            let cell = &graph.cells[cell];
+            open += !cell.blocked as usize;
+            items += cell.food.len();
+            for &kind in &cell.kinds {
+                kinds[kind] += 1;
+            }
+        }
+        (open, items, kinds)
+    }
+
+    #[test]
+    fn lone_cell_stays_in_place() {
+        let graph = BoardGraph::new(vec![[0.; 3]], [Vec::new()], 1);
+        let pos = BoardPosition::new(0);
+        let mut rng = SmallRng::seed_from_u64(7);
+        assert_eq!(graph.get_random_adjacent(&pos, &mut rng), pos);
+    }
+
+    #[test]
+    fn vision_counts_match_recount() {
+        let mut graph = torus(2);
+        graph.add_vision(1);
+        let mut rng = SmallRng::seed_from_u64(7);
+        let mut next_item = 0;
+        for step in 0..5000 {
+            let pos = BoardPosition::new(rng.gen_range(0..graph.size()));
+            match rng.gen_range(0..4) {
+                0 if !graph.is_blocked(&pos) && !graph.is_full(&pos) => {
+                    graph.place_item(&pos, next_item, rng.gen_range(0..ITEM_KINDS));
+                    next_item += 1;
+                }
+                1 => {
+                    graph.take_item(&pos);
+                }
+                2 => {
+                    let food = &graph.get_cell(&pos).food;
+                    if let Some(&item) = food.get(rng.gen_range(0..food.len().max(1))) {
+                        assert!(graph.remove_item(&pos, item));
+                    }
+                }
+                3 if graph.get_cell(&pos).food.is_empty() => {
+                    graph.set_blocked(&pos, !graph.is_blocked(&pos));
+                }
+                _ => {}
+            }
+            if step % 100 != 0 {
+                continue;
+            }
+            for idx in 0..graph.size() {
+                let pos = BoardPosition::new(idx);
+                let (open, items, _) = recount(&graph, idx, 2);
+                assert_eq!(graph.vision_count(&pos), open);
+                assert_eq!(graph.items_in_vision(&pos), items);
+                for radius in [1, 2, 3] {
+                    let (open, items, kinds) = recount(&graph, idx, radius);
+                    let density = |items: usize| match open {
+                        0 => 0.,
+                        open => items as f64 / (open * graph.stack_height) as f64,
+                    };
+                    assert_eq!(
+                        graph.item_density_within(&pos, radius, None),
+                        density(items)
+                    );
+                    for (kind, &count) in kinds.iter().enumerate() {
+                        assert_eq!(
+                            graph.item_density_within(&pos, radius, Some(&[kind])),
+                            density(count)
+                        );
+                    }
+                }
+            }
+        }
+    }
+}
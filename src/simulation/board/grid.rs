@@ -0,0 +1,107 @@
+use bevy::prelude::*;
+
+use crate::consts::BOARD_RADIUS;
+use crate::simulation::board::{Board, BoardGraph, BoardPosition};
+
+/// Flat grid of square cells, each one adjacent to the 8 cells around it.
+/// When `toroidal`, the edges wrap around
+pub struct SquareGrid {
+    pub graph: BoardGraph,
+    pub width: usize,
+    pub height: usize,
+    pub toroidal: bool,
+}
+
+impl SquareGrid {
+    /// Column and row offsets of the 8 neighbours
+    const NEIGHBOURS: [(isize, isize); 8] = [
+        (-1, -1),
+        (0, -1),
+        (1, -1),
+        (-1, 0),
+        (1, 0),
+        (-1, 1),
+        (0, 1),
+        (1, 1),
+    ];
+
+    pub fn new(width: usize, height: usize, toroidal: bool, vision_radius: usize) -> Self {
+        let spacing = 2. * BOARD_RADIUS / width.max(height) as f32;
+        let vertex = (0..width * height)
+            .map(|i| {
+                let (col, row) = (i % width, i / width);
+                [
+                    (col as f32 - (width - 1) as f32 / 2.) * spacing,
+                    (row as f32 - (height - 1) as f32 / 2.) * spacing,
+                    0.,
+                ]
+            })
+            .collect();
+
+        let adjacency = (0..width * height).map(|i| {
+            let (col, row) = ((i % width) as isize, (i / width) as isize);
+            let mut neighbours = Vec::with_capacity(8);
+            for (dc, dr) in Self::NEIGHBOURS {
+                let (mut c, mut r) = (col + dc, row + dr);
+                if toroidal {
+                    c = c.rem_euclid(width as isize);
+                    r = r.rem_euclid(height as isize);
+                } else if c < 0 || r < 0 || c >= width as isize || r >= height as isize {
+                    continue;
+                }
+                neighbours.push((r as usize * width + c as usize) as u32);
+            }
+            neighbours
+        });
+
+        Self {
+            graph: BoardGraph::new(vertex, adjacency, vision_radius),
+            width,
+            height,
+            toroidal,
+        }
+    }
+}
+
+impl Board for SquareGrid {
+    fn graph(&self) -> &BoardGraph {
+        &self.graph
+    }
+
+    fn graph_mut(&mut self) -> &mut BoardGraph {
+        &mut self.graph
+    }
+
+    fn normal(&self, _pos: &BoardPosition) -> [f32; 3] {
+        [0., 0., 1.]
+    }
+
+    fn offset(&self, from: &BoardPosition, to: &BoardPosition) -> [f32; 3] {
+        let mut offset =
+            Vec3::from(self.world_position(to)) - Vec3::from(self.world_position(from));
+        if self.toroidal {
+            let spacing = 2. * BOARD_RADIUS / self.width.max(self.height) as f32;
+            let (width, height) = (self.width as f32 * spacing, self.height as f32 * spacing);
+            offset.x -= (offset.x / width).round() * width;
+            offset.y -= (offset.y / height).round() * height;
+        }
+        offset.into()
+    }
+
+    /// Images cover the whole grid, with the first row at the bottom
+    fn uv(&self, pos: &BoardPosition) -> [f32; 2] {
+        let (col, row) = (pos.idx() % self.width, pos.idx() / self.width);
+        [
+            (col as f32 + 0.5) / self.width as f32,
+            1. - (row as f32 + 0.5) / self.height as f32,
+        ]
+    }
+
+    fn mesh(&self) -> Mesh {
+        let spacing = 2. * BOARD_RADIUS / self.width.max(self.height) as f32;
+        Mesh::from(shape::Quad::new(Vec2::new(
+            self.width as f32 * spacing,
+            self.height as f32 * spacing,
+        )))
+    }
+}
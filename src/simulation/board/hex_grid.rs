@@ -0,0 +1,93 @@
+use bevy::prelude::*;
+
+use crate::consts::BOARD_RADIUS;
+use crate::simulation::board::{Board, BoardGraph, BoardPosition};
+
+/// Flat grid of hexagonal cells, with odd rows shifted half a cell to the right
+pub struct HexGrid {
+    pub graph: BoardGraph,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl HexGrid {
+    /// Column and row offsets of the 6 neighbours, for even and odd rows
+    const EVEN_ROW_NEIGHBOURS: [(isize, isize); 6] =
+        [(-1, 0), (1, 0), (-1, -1), (0, -1), (-1, 1), (0, 1)];
+    const ODD_ROW_NEIGHBOURS: [(isize, isize); 6] =
+        [(-1, 0), (1, 0), (0, -1), (1, -1), (0, 1), (1, 1)];
+
+    pub fn new(width: usize, height: usize, vision_radius: usize) -> Self {
+        let (spacing, row_spacing) = Self::spacing(width, height);
+        let vertex = (0..width * height)
+            .map(|i| {
+                let (col, row) = (i % width, i / width);
+                let shift = if row % 2 == 1 { 0.5 } else { 0. };
+                [
+                    (col as f32 + shift - (width as f32 - 0.5) / 2.) * spacing,
+                    (row as f32 - (height - 1) as f32 / 2.) * row_spacing,
+                    0.,
+                ]
+            })
+            .collect();
+
+        let adjacency = (0..width * height).map(|i| {
+            let (col, row) = ((i % width) as isize, (i / width) as isize);
+            let offsets = if row % 2 == 1 {
+                Self::ODD_ROW_NEIGHBOURS
+            } else {
+                Self::EVEN_ROW_NEIGHBOURS
+            };
+            offsets
+                .into_iter()
+                .map(move |(dc, dr)| (col + dc, row + dr))
+                .filter(|&(c, r)| c >= 0 && r >= 0 && c < width as isize && r < height as isize)
+                .map(|(c, r)| (r as usize * width + c as usize) as u32)
+                .collect::<Vec<_>>()
+        });
+
+        Self {
+            graph: BoardGraph::new(vertex, adjacency, vision_radius),
+            width,
+            height,
+        }
+    }
+
+    /// Distance between adjacent cells and between adjacent rows, fitting the grid in the board radius
+    fn spacing(width: usize, height: usize) -> (f32, f32) {
+        let row_factor = 3f32.sqrt() / 2.;
+        let spacing = 2. * BOARD_RADIUS / (width as f32 + 0.5).max(height as f32 * row_factor);
+        (spacing, spacing * row_factor)
+    }
+}
+
+impl Board for HexGrid {
+    fn graph(&self) -> &BoardGraph {
+        &self.graph
+    }
+
+    fn graph_mut(&mut self) -> &mut BoardGraph {
+        &mut self.graph
+    }
+
+    fn normal(&self, _pos: &BoardPosition) -> [f32; 3] {
+        [0., 0., 1.]
+    }
+
+    /// Images cover the whole grid, with the first row at the bottom
+    fn uv(&self, pos: &BoardPosition) -> [f32; 2] {
+        let (col, row) = (pos.idx() % self.width, pos.idx() / self.width);
+        [
+            (col as f32 + 0.5) / self.width as f32,
+            1. - (row as f32 + 0.5) / self.height as f32,
+        ]
+    }
+
+    fn mesh(&self) -> Mesh {
+        let (spacing, row_spacing) = Self::spacing(self.width, self.height);
+        Mesh::from(shape::Quad::new(Vec2::new(
+            (self.width as f32 + 0.5) * spacing,
+            self.height as f32 * row_spacing,
+        )))
+    }
+}
@@ -0,0 +1,173 @@
+use bevy::prelude::*;
+use bevy::render::mesh::Indices;
+use bevy::render::render_resource::PrimitiveTopology;
+use std::ops::Range;
+
+use crate::consts::BOARD_RADIUS;
+use crate::simulation::board::{Board, BoardGraph, BoardPosition, IcoBoard};
+
+/// Colour of the cells without an item, on boards with vertex colours
+pub const EMPTY_CELL_COLOR: Color = Color::GREEN;
+
+/// Board whose cells are the faces of the dual of an icosphere:
+/// 12 pentagons and hexagons everywhere else, each drawn as its own polygon
+#[derive(Debug, Default, Clone)]
+pub struct HexaSphereBoard {
+    pub graph: BoardGraph,
+    /// Cell `i` is drawn with the mesh vertices `cell_offsets[i]..cell_offsets[i + 1]`
+    cell_offsets: Vec<u32>,
+    positions: Vec<[f32; 3]>,
+    /// Cells whose elevation is averaged to raise each mesh vertex,
+    /// so neighbouring polygons stay joined on a terrain
+    position_cells: Vec<[u32; 3]>,
+    indices: Vec<u32>,
+}
+
+impl HexaSphereBoard {
+    /// Every vertex of the icosphere becomes the center of a cell,
+    /// whose corners are the centers of the triangles around it
+    pub fn new(subdivisions: usize, vision_radius: usize) -> Self {
+        let ico = IcoBoard::from_mesh(
+            &Mesh::from(shape::Icosphere {
+                radius: BOARD_RADIUS,
+                subdivisions,
+            }),
+            vision_radius,
+        );
+        let vertex = &ico.graph.vertex;
+
+        let corners: Vec<Vec3> = ico
+            .indices
+            .chunks_exact(3)
+            .map(|triangle| {
+                let center = triangle
+                    .iter()
+                    .fold(Vec3::ZERO, |sum, &i| sum + Vec3::from(vertex[i as usize]));
+                center.normalize() * BOARD_RADIUS
+            })
+            .collect();
+
+        let mut triangles_around = vec![Vec::new(); vertex.len()];
+        for (t, triangle) in ico.indices.chunks_exact(3).enumerate() {
+            for &i in triangle {
+                triangles_around[i as usize].push(t);
+            }
+        }
+
+        let mut cell_offsets = Vec::with_capacity(vertex.len() + 1);
+        let mut positions = Vec::new();
+        let mut position_cells = Vec::new();
+        let mut indices = Vec::new();
+        cell_offsets.push(0);
+        for (i, around) in triangles_around.iter_mut().enumerate() {
+            let center = Vec3::from(vertex[i]);
+            let normal = center.normalize();
+            let reference = if normal.x.abs() < 0.9 {
+                Vec3::X
+            } else {
+                Vec3::Y
+            };
+            let tangent = normal.cross(reference).normalize();
+            let bitangent = normal.cross(tangent);
+
+            // Counter clockwise around the normal, so the fan faces outwards
+            let angle = |t: &usize| {
+                let offset = corners[*t] - center;
+                offset.dot(bitangent).atan2(offset.dot(tangent))
+            };
+            around.sort_by(|a, b| angle(a).total_cmp(&angle(b)));
+
+            let base = positions.len() as u32;
+            let sides = around.len() as u32;
+            positions.push(center.into());
+            positions.extend(around.iter().map(|&t| <[f32; 3]>::from(corners[t])));
+            position_cells.push([i as u32; 3]);
+            position_cells.extend(around.iter().map(|&t| {
+                let triangle = &ico.indices[t * 3..t * 3 + 3];
+                [triangle[0], triangle[1], triangle[2]]
+            }));
+            for k in 0..sides {
+                indices.extend([base, base + 1 + k, base + 1 + (k + 1) % sides]);
+            }
+            cell_offsets.push(positions.len() as u32);
+        }
+
+        Self {
+            graph: ico.graph,
+            cell_offsets,
+            positions,
+            position_cells,
+            indices,
+        }
+    }
+}
+
+impl Board for HexaSphereBoard {
+    fn graph(&self) -> &BoardGraph {
+        &self.graph
+    }
+
+    fn graph_mut(&mut self) -> &mut BoardGraph {
+        &mut self.graph
+    }
+
+    fn normal(&self, pos: &BoardPosition) -> [f32; 3] {
+        Vec3::from(self.world_position(pos)).normalize().into()
+    }
+
+    /// Raises the cell centers by their elevation and the corners by the mean elevation
+    /// of the three cells meeting there
+    fn set_elevation(&mut self, elevation: Vec<f32>) {
+        let offset = |cells: &[u32; 3]| {
+            cells
+                .iter()
+                .map(|&i| {
+                    elevation[i as usize] - self.graph.elevation(&BoardPosition::new(i as usize))
+                })
+                .sum::<f32>()
+                / 3.
+        };
+        let positions = self
+            .positions
+            .iter()
+            .zip(&self.position_cells)
+            .map(|(&position, cells)| {
+                let position = Vec3::from(position);
+                (position + position.normalize() * offset(cells)).into()
+            })
+            .collect();
+        self.positions = positions;
+
+        let normals: Vec<[f32; 3]> = self
+            .graph
+            .vertex
+            .iter()
+            .map(|&vertex| Vec3::from(vertex).normalize().into())
+            .collect();
+        self.graph.set_elevation(elevation, Some(&normals));
+    }
+
+    fn mesh(&self) -> Mesh {
+        let normals: Vec<[f32; 3]> = self
+            .positions
+            .iter()
+            .map(|&p| Vec3::from(p).normalize().into())
+            .collect();
+        let uvs = vec![[0., 0.]; self.positions.len()];
+        let colors = vec![EMPTY_CELL_COLOR.as_rgba_f32(); self.positions.len()];
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions.clone());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        mesh.set_indices(Some(Indices::U32(self.indices.clone())));
+        mesh
+    }
+
+    fn cell_vertices(&self, pos: &BoardPosition) -> Option<Range<usize>> {
+        let start = self.cell_offsets[pos.idx()] as usize;
+        let end = self.cell_offsets[pos.idx() + 1] as usize;
+        Some(start..end)
+    }
+}
@@ -1,113 +1,112 @@
-use crate::consts::{BOARD_RADIUS, BOARD_SUBDIVISIONS};
-use crate::BoardPosition;
-pub use bevy::prelude::*;
+use bevy::prelude::*;
 use bevy::render::mesh::{Indices, VertexAttributeValues};
+use bevy::render::render_resource::PrimitiveTopology;
 use bevy_inspector_egui::Inspectable;
 use hexasphere::AdjacentStore;
-use rand::prelude::SliceRandom;
-use rand::Rng;
+use std::ops::Range;
+use std::path::Path;
 
-use crate::simulation::board::Cell;
-
-#[derive(Component)]
-pub struct BoardSphere;
+use crate::simulation::board::{
+    load_triangle_mesh, Board, BoardGraph, BoardPosition, MeshLoadError, EMPTY_CELL_COLOR,
+};
 
+/// Board whose cells are the vertices of a triangle mesh
 #[derive(Debug, Default, Clone, Reflect, Inspectable)]
-#[reflect(Resource)]
 pub struct IcoBoard {
-    pub adj: Vec<Vec<usize>>,
-    pub vertex: Vec<[f32; 3]>,
+    pub graph: BoardGraph,
     pub indices: Vec<u32>,
-    pub cells: Vec<Cell>,
+    /// Area weighted mean of the normals of the triangles around each vertex
+    pub normals: Vec<[f32; 3]>,
 }
 
 impl IcoBoard {
-    pub fn new_random_position(&self) -> BoardPosition {
-        let mut rng = rand::thread_rng();
-        let idx = rng.gen_range(0..self.size());
-        BoardPosition::new(idx)
+    /// Builds the board graph from the vertices and triangles of a mesh,
+    /// with cells seeing the items up to `vision_radius` edges away
+    pub fn from_mesh(mesh: &Mesh, vision_radius: usize) -> Self {
+        let vertex = match mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap() {
+            VertexAttributeValues::Float32x3(v) => v,
+            _ => panic!("Unexpected Attribute Format"),
+        };
+
+        let indices = match mesh.indices().unwrap() {
+            Indices::U32(v) => v,
+            Indices::U16(_) => panic!("Unexpected U16 indices"),
+        };
+
+        Self::from_triangles(vertex.clone(), indices.clone(), vision_radius)
     }
 
-    pub fn size(&self) -> usize {
-        self.cells.len()
+    /// Loads an OBJ, PLY or glTF triangle mesh as the board, see [`load_triangle_mesh`]
+    pub fn load(path: &Path, vision_radius: usize) -> Result<Self, MeshLoadError> {
+        let (vertex, indices) = load_triangle_mesh(path)?;
+        Ok(Self::from_triangles(vertex, indices, vision_radius))
     }
 
-    pub fn get_cell(&self, pos: &BoardPosition) -> &Cell {
-        &self.cells[pos.idx()]
+    /// Every vertex must belong to a triangle
+    pub fn from_triangles(vertex: Vec<[f32; 3]>, indices: Vec<u32>, vision_radius: usize) -> Self {
+        let normals = Self::vertex_normals(&vertex, &indices);
+
+        let adj_store = AdjacentStore::from_indices(&indices);
+        let adjacency =
+            (0..vertex.len()).map(|i| adj_store.neighbours(i as u32).unwrap().iter().copied());
+
+        Self {
+            graph: BoardGraph::new(vertex, adjacency, vision_radius),
+            indices,
+            normals,
+        }
     }
 
-    pub fn get_cell_mut(&mut self, pos: &BoardPosition) -> &mut Cell {
-        &mut self.cells[pos.idx()]
+    fn vertex_normals(vertex: &[[f32; 3]], indices: &[u32]) -> Vec<[f32; 3]> {
+        let mut normals = vec![Vec3::ZERO; vertex.len()];
+        for triangle in indices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| Vec3::from(vertex[triangle[i] as usize]));
+            let normal = (b - a).cross(c - a);
+            for &i in triangle {
+                normals[i as usize] += normal;
+            }
+        }
+        normals
+            .into_iter()
+            .map(|normal| normal.normalize_or_zero().into())
+            .collect()
     }
+}
 
-    pub fn world_position(&self, pos: &BoardPosition) -> [f32; 3] {
-        self.vertex[pos.idx()]
+impl Board for IcoBoard {
+    fn graph(&self) -> &BoardGraph {
+        &self.graph
     }
 
-    pub fn get_all_adjacent(&self, pos: &BoardPosition) -> Vec<BoardPosition> {
-        self.adj[pos.idx()]
-            .iter()
-            .map(|f| BoardPosition::new(*f))
-            .collect()
+    fn graph_mut(&mut self) -> &mut BoardGraph {
+        &mut self.graph
     }
 
-    pub fn get_random_adjacent(&self, pos: &BoardPosition) -> BoardPosition {
-        let mut rng = rand::thread_rng();
-        let idx = self.adj[pos.idx()].choose(&mut rng).unwrap();
-        BoardPosition::new(*idx)
+    fn normal(&self, pos: &BoardPosition) -> [f32; 3] {
+        self.normals[pos.idx()]
+    }
+
+    /// Moves the vertices along their normals, then recomputes the normals of the relief
+    fn set_elevation(&mut self, elevation: Vec<f32>) {
+        self.graph.set_elevation(elevation, Some(&self.normals));
+        self.normals = Self::vertex_normals(&self.graph.vertex, &self.indices);
     }
-}
 
-pub fn icosphere_setup(
-    mut commands: Commands,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-) {
-    let mesh = Mesh::from(shape::Icosphere {
-        radius: BOARD_RADIUS,
-        subdivisions: BOARD_SUBDIVISIONS,
-    });
-
-    let vertex = match mesh.attribute(Mesh::ATTRIBUTE_POSITION).unwrap() {
-        VertexAttributeValues::Float32x3(v) => v,
-        _ => panic!("Unexpected Attribute Format"),
-    };
-
-    let indices = match mesh.indices().unwrap() {
-        Indices::U32(v) => v,
-        Indices::U16(_) => panic!("Unexpected U16 indices"),
-    };
-
-    let adj_store = AdjacentStore::from_indices(indices);
-    let adj = (0..vertex.len())
-        .map(|i| {
-            adj_store
-                .neighbours(i as u32)
-                .unwrap()
-                .iter()
-                .map(|i| *i as usize)
-                .collect()
-        })
-        .collect();
-
-    info!("Board with {} positions!", vertex.len());
-
-    let cells = vec![Cell::default(); vertex.len()];
-
-    commands.insert_resource(IcoBoard {
-        adj,
-        vertex: vertex.clone(),
-        indices: indices.clone(),
-        cells,
-    });
-
-    let mesh_handle = meshes.add(mesh);
-
-    commands
-        .spawn_bundle(PbrBundle {
-            mesh: mesh_handle,
-            material: materials.add(Color::GREEN.into()),
-            ..default()
-        })
-        .insert(BoardSphere);
+    /// The mesh vertices are the cells
+    fn colored_vertices(&self, pos: &BoardPosition) -> Option<Range<usize>> {
+        Some(pos.idx()..pos.idx() + 1)
+    }
+
+    fn mesh(&self) -> Mesh {
+        let uvs = vec![[0., 0.]; self.size()];
+        let colors = vec![EMPTY_CELL_COLOR.as_rgba_f32(); self.size()];
+
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.graph.vertex.clone());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals.clone());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_UV_0, uvs);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        mesh.set_indices(Some(Indices::U32(self.indices.clone())));
+        mesh
+    }
 }
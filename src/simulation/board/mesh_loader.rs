@@ -0,0 +1,563 @@
+//! Loading of triangle meshes from OBJ, PLY and glTF files to be used as boards
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::consts::BOARD_RADIUS;
+
+#[derive(Debug)]
+pub enum MeshLoadError {
+    Io(std::io::Error),
+    /// The file could not be parsed
+    Format(String),
+    /// Unknown file extension or unsupported content, such as non triangle primitives
+    Unsupported(String),
+    Empty,
+    /// An edge is shared by more than two triangles
+    NonManifold {
+        edge: (u32, u32),
+        triangles: usize,
+    },
+    /// The triangles around a vertex form several separate fans, like two cones touching
+    NonManifoldVertex {
+        vertex: u32,
+        fans: usize,
+    },
+    /// The board graph would have cells the ants can't reach
+    Disconnected {
+        components: usize,
+    },
+}
+
+impl fmt::Display for MeshLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MeshLoadError::Io(e) => write!(f, "Could not read mesh: {e}"),
+            MeshLoadError::Format(e) => write!(f, "Could not parse mesh: {e}"),
+            MeshLoadError::Unsupported(e) => write!(f, "Unsupported mesh: {e}"),
+            MeshLoadError::Empty => write!(f, "Mesh has no triangles"),
+            MeshLoadError::NonManifold { edge, triangles } => write!(
+                f,
+                "Mesh is not manifold: edge between vertices {} and {} is shared by {} triangles",
+                edge.0, edge.1, triangles
+            ),
+            MeshLoadError::NonManifoldVertex { vertex, fans } => write!(
+                f,
+                "Mesh is not manifold: the triangles around vertex {vertex} form {fans} separate fans"
+            ),
+            MeshLoadError::Disconnected { components } => write!(
+                f,
+                "Mesh is not connected: found {components} separate components"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MeshLoadError {}
+
+impl From<std::io::Error> for MeshLoadError {
+    fn from(e: std::io::Error) -> Self {
+        MeshLoadError::Io(e)
+    }
+}
+
+/// Loads the triangles of an OBJ, PLY or glTF file, chosen by extension.
+/// Vertices sharing a position are merged, unused vertices are dropped and the mesh is
+/// centered and scaled to fit the board radius.
+/// Non-manifold and disconnected meshes are rejected
+pub fn load_triangle_mesh(path: &Path) -> Result<(Vec<[f32; 3]>, Vec<u32>), MeshLoadError> {
+    let extension = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    let (vertex, indices) = match extension.as_deref() {
+        Some("obj") => load_obj(path)?,
+        Some("ply") => load_ply(path)?,
+        Some("gltf" | "glb") => load_gltf(path)?,
+        _ => {
+            return Err(MeshLoadError::Unsupported(format!(
+                "{} is not an obj, ply, gltf or glb file",
+                path.display()
+            )))
+        }
+    };
+
+    if let Some(&index) = indices.iter().find(|&&i| i as usize >= vertex.len()) {
+        return Err(MeshLoadError::Format(format!(
+            "Vertex index {index} out of bounds, mesh has {} vertices",
+            vertex.len()
+        )));
+    }
+
+    let (mut vertex, indices) = weld(&vertex, &indices);
+    validate(vertex.len(), &indices)?;
+    fit_to_board(&mut vertex);
+    Ok((vertex, indices))
+}
+
+fn load_obj(path: &Path) -> Result<(Vec<[f32; 3]>, Vec<u32>), MeshLoadError> {
+    let (models, _) = tobj::load_obj(
+        path,
+        &tobj::LoadOptions {
+            triangulate: true,
+            ..Default::default()
+        },
+    )
+    .map_err(|e| MeshLoadError::Format(e.to_string()))?;
+
+    let mut vertex = Vec::new();
+    let mut indices = Vec::new();
+    for model in models {
+        let offset = vertex.len() as u32;
+        vertex.extend(
+            model
+                .mesh
+                .positions
+                .chunks_exact(3)
+                .map(|p| [p[0], p[1], p[2]]),
+        );
+        indices.extend(model.mesh.indices.iter().map(|i| i + offset));
+    }
+    Ok((vertex, indices))
+}
+
+/// Only the positions of mesh primitives are read, node transforms are ignored
+fn load_gltf(path: &Path) -> Result<(Vec<[f32; 3]>, Vec<u32>), MeshLoadError> {
+    let (document, buffers, _) =
+        gltf::import(path).map_err(|e| MeshLoadError::Format(e.to_string()))?;
+
+    let mut vertex = Vec::new();
+    let mut indices = Vec::new();
+    for mesh in document.meshes() {
+        for primitive in mesh.primitives() {
+            if primitive.mode() != gltf::mesh::Mode::Triangles {
+                return Err(MeshLoadError::Unsupported(format!(
+                    "{:?} primitives, only triangles are supported",
+                    primitive.mode()
+                )));
+            }
+            let reader = primitive.reader(|buffer| Some(&buffers[buffer.index()]));
+            let offset = vertex.len() as u32;
+            vertex.extend(
+                reader.read_positions().ok_or_else(|| {
+                    MeshLoadError::Format("Primitive without positions".to_string())
+                })?,
+            );
+            match reader.read_indices() {
+                Some(read) => indices.extend(read.into_u32().map(|i| i + offset)),
+                None => indices.extend(offset..vertex.len() as u32),
+            }
+        }
+    }
+    Ok((vertex, indices))
+}
+
+#[derive(Debug, Copy, Clone)]
+enum PlyType {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl PlyType {
+    fn parse(name: &str) -> Result<Self, MeshLoadError> {
+        Ok(match name {
+            "char" | "int8" => PlyType::I8,
+            "uchar" | "uint8" => PlyType::U8,
+            "short" | "int16" => PlyType::I16,
+            "ushort" | "uint16" => PlyType::U16,
+            "int" | "int32" => PlyType::I32,
+            "uint" | "uint32" => PlyType::U32,
+            "float" | "float32" => PlyType::F32,
+            "double" | "float64" => PlyType::F64,
+            _ => return Err(MeshLoadError::Format(format!("Unknown ply type {name}"))),
+        })
+    }
+
+    fn size(&self) -> usize {
+        match self {
+            PlyType::I8 | PlyType::U8 => 1,
+            PlyType::I16 | PlyType::U16 => 2,
+            PlyType::I32 | PlyType::U32 | PlyType::F32 => 4,
+            PlyType::F64 => 8,
+        }
+    }
+}
+
+enum PlyProperty {
+    Scalar(String, PlyType),
+    List(String, PlyType, PlyType),
+}
+
+struct PlyElement {
+    name: String,
+    count: usize,
+    properties: Vec<PlyProperty>,
+}
+
+/// Reads the values of a ply body, either ascii or binary
+enum PlyBody<'a> {
+    Ascii(std::str::SplitAsciiWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl PlyBody<'_> {
+    fn read(&mut self, ty: PlyType) -> Result<f64, MeshLoadError> {
+        let truncated = || MeshLoadError::Format("Truncated ply body".to_string());
+        match self {
+            PlyBody::Ascii(tokens) => {
+                let token = tokens.next().ok_or_else(truncated)?;
+                token
+                    .parse()
+                    .map_err(|_| MeshLoadError::Format(format!("Invalid ply value {token}")))
+            }
+            PlyBody::Binary { bytes, big_endian } => {
+                if bytes.len() < ty.size() {
+                    return Err(truncated());
+                }
+                let data = *bytes;
+                let (value, rest) = data.split_at(ty.size());
+                *bytes = rest;
+                let mut buf = [0u8; 8];
+                buf[..value.len()].copy_from_slice(value);
+                if *big_endian {
+                    buf[..value.len()].reverse();
+                }
+                Ok(match ty {
+                    PlyType::I8 => buf[0] as i8 as f64,
+                    PlyType::U8 => buf[0] as f64,
+                    PlyType::I16 => i16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    PlyType::U16 => u16::from_le_bytes([buf[0], buf[1]]) as f64,
+                    PlyType::I32 => i32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    PlyType::U32 => u32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    PlyType::F32 => f32::from_le_bytes([buf[0], buf[1], buf[2], buf[3]]) as f64,
+                    PlyType::F64 => f64::from_le_bytes(buf),
+                })
+            }
+        }
+    }
+}
+
+fn load_ply(path: &Path) -> Result<(Vec<[f32; 3]>, Vec<u32>), MeshLoadError> {
+    parse_ply(&std::fs::read(path)?)
+}
+
+/// Reads the `vertex` element positions and the `face` element `vertex_indices`,
+/// polygons are split in triangle fans
+fn parse_ply(bytes: &[u8]) -> Result<(Vec<[f32; 3]>, Vec<u32>), MeshLoadError> {
+    let header_end = bytes
+        .windows(b"end_header".len())
+        .position(|w| w == b"end_header")
+        .ok_or_else(|| MeshLoadError::Format("Missing end_header".to_string()))?;
+    let header = std::str::from_utf8(&bytes[..header_end])
+        .map_err(|_| MeshLoadError::Format("Header is not valid text".to_string()))?;
+    let body_start = bytes[header_end..]
+        .iter()
+        .position(|&b| b == b'\n')
+        .map(|p| header_end + p + 1)
+        .unwrap_or(bytes.len());
+
+    let mut lines = header.lines().map(str::trim);
+    if lines.next() != Some("ply") {
+        return Err(MeshLoadError::Format(
+            "Missing ply magic number".to_string(),
+        ));
+    }
+    let mut format = None;
+    let mut elements: Vec<PlyElement> = Vec::new();
+    for line in lines {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        match words.as_slice() {
+            ["format", format_name, _] => format = Some(format_name.to_string()),
+            ["element", name, count] => elements.push(PlyElement {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| MeshLoadError::Format(format!("Invalid count {count}")))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", count_ty, item_ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| MeshLoadError::Format("Property before element".to_string()))?
+                .properties
+                .push(PlyProperty::List(
+                    name.to_string(),
+                    PlyType::parse(count_ty)?,
+                    PlyType::parse(item_ty)?,
+                )),
+            ["property", ty, name] => elements
+                .last_mut()
+                .ok_or_else(|| MeshLoadError::Format("Property before element".to_string()))?
+                .properties
+                .push(PlyProperty::Scalar(name.to_string(), PlyType::parse(ty)?)),
+            _ => {}
+        }
+    }
+
+    let mut body = match format.as_deref() {
+        Some("ascii") => PlyBody::Ascii(
+            std::str::from_utf8(&bytes[body_start..])
+                .map_err(|_| MeshLoadError::Format("Body is not valid text".to_string()))?
+                .split_ascii_whitespace(),
+        ),
+        Some("binary_little_endian") => PlyBody::Binary {
+            bytes: &bytes[body_start..],
+            big_endian: false,
+        },
+        Some("binary_big_endian") => PlyBody::Binary {
+            bytes: &bytes[body_start..],
+            big_endian: true,
+        },
+        _ => return Err(MeshLoadError::Format("Unknown ply format".to_string())),
+    };
+
+    let mut vertex = Vec::new();
+    let mut indices = Vec::new();
+    for element in &elements {
+        for _ in 0..element.count {
+            let mut position = [0f32; 3];
+            for property in &element.properties {
+                match property {
+                    PlyProperty::Scalar(name, ty) => {
+                        let value = body.read(*ty)?;
+                        match (element.name.as_str(), name.as_str()) {
+                            ("vertex", "x") => position[0] = value as f32,
+                            ("vertex", "y") => position[1] = value as f32,
+                            ("vertex", "z") => position[2] = value as f32,
+                            _ => {}
+                        }
+                    }
+                    PlyProperty::List(name, count_ty, item_ty) => {
+                        let count = body.read(*count_ty)? as usize;
+                        let mut polygon = Vec::with_capacity(count);
+                        for _ in 0..count {
+                            polygon.push(body.read(*item_ty)? as u32);
+                        }
+                        let is_face = element.name == "face"
+                            && (name == "vertex_indices" || name == "vertex_index");
+                        if is_face {
+                            for i in 1..polygon.len().saturating_sub(1) {
+                                indices.extend([polygon[0], polygon[i], polygon[i + 1]]);
+                            }
+                        }
+                    }
+                }
+            }
+            if element.name == "vertex" {
+                vertex.push(position);
+            }
+        }
+    }
+    Ok((vertex, indices))
+}
+
+/// Merges vertices with the same position, dropping unused vertices and degenerate triangles
+fn weld(vertex: &[[f32; 3]], indices: &[u32]) -> (Vec<[f32; 3]>, Vec<u32>) {
+    let mut welded_vertex = Vec::new();
+    let mut welded_index: HashMap<[u32; 3], u32> = HashMap::new();
+    let mut remap = |i: u32| {
+        let position = vertex[i as usize];
+        *welded_index
+            .entry(position.map(f32::to_bits))
+            .or_insert_with(|| {
+                welded_vertex.push(position);
+                welded_vertex.len() as u32 - 1
+            })
+    };
+
+    // Degenerate triangles are dropped before remapping, so that vertices only they use are too
+    let mut welded_indices = Vec::with_capacity(indices.len());
+    for triangle in indices.chunks_exact(3) {
+        let [a, b, c] = [0, 1, 2].map(|i| vertex[triangle[i] as usize].map(f32::to_bits));
+        if a != b && b != c && c != a {
+            welded_indices.extend(triangle.iter().map(|&i| remap(i)));
+        }
+    }
+    (welded_vertex, welded_indices)
+}
+
+fn validate(vertex_count: usize, indices: &[u32]) -> Result<(), MeshLoadError> {
+    if indices.is_empty() {
+        return Err(MeshLoadError::Empty);
+    }
+
+    let mut edges: HashMap<(u32, u32), usize> = HashMap::new();
+    for triangle in indices.chunks_exact(3) {
+        for (a, b) in [
+            (triangle[0], triangle[1]),
+            (triangle[1], triangle[2]),
+            (triangle[2], triangle[0]),
+        ] {
+            *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+        }
+    }
+    if let Some((&edge, &triangles)) = edges.iter().find(|(_, &count)| count > 2) {
+        return Err(MeshLoadError::NonManifold { edge, triangles });
+    }
+
+    // The edges facing a vertex in its triangles must link up into a single fan
+    let mut facing: Vec<Vec<(u32, u32)>> = vec![Vec::new(); vertex_count];
+    for triangle in indices.chunks_exact(3) {
+        for k in 0..3 {
+            facing[triangle[k] as usize].push((triangle[(k + 1) % 3], triangle[(k + 2) % 3]));
+        }
+    }
+    for (vertex, edges) in facing.iter().enumerate() {
+        let mut around: Vec<u32> = edges.iter().flat_map(|&(a, b)| [a, b]).collect();
+        around.sort_unstable();
+        around.dedup();
+        let mut parent: Vec<usize> = (0..around.len()).collect();
+        let mut fans = around.len();
+        for &(a, b) in edges {
+            let a = root(&mut parent, around.binary_search(&a).unwrap());
+            let b = root(&mut parent, around.binary_search(&b).unwrap());
+            if a != b {
+                parent[a] = b;
+                fans -= 1;
+            }
+        }
+        if fans > 1 {
+            return Err(MeshLoadError::NonManifoldVertex {
+                vertex: vertex as u32,
+                fans,
+            });
+        }
+    }
+
+    // Union find over the edges
+    let mut parent: Vec<usize> = (0..vertex_count).collect();
+    for &(a, b) in edges.keys() {
+        let (a, b) = (root(&mut parent, a as usize), root(&mut parent, b as usize));
+        parent[a] = b;
+    }
+    let components = (0..vertex_count)
+        .filter(|&i| root(&mut parent, i) == i)
+        .count();
+    if components > 1 {
+        return Err(MeshLoadError::Disconnected { components });
+    }
+    Ok(())
+}
+
+/// Root of the set of `i` in a union find, compressing the path on the way
+fn root(parent: &mut [usize], mut i: usize) -> usize {
+    while parent[i] != i {
+        parent[i] = parent[parent[i]];
+        i = parent[i];
+    }
+    i
+}
+
+/// Centers the mesh on the origin and scales it so its farthest vertex is at the board radius
+fn fit_to_board(vertex: &mut [[f32; 3]]) {
+    let mut min = [f32::MAX; 3];
+    let mut max = [f32::MIN; 3];
+    for v in vertex.iter() {
+        for axis in 0..3 {
+            min[axis] = min[axis].min(v[axis]);
+            max[axis] = max[axis].max(v[axis]);
+        }
+    }
+    let center = [0, 1, 2].map(|axis| (min[axis] + max[axis]) / 2.);
+    let radius = vertex
+        .iter()
+        .map(|v| {
+            (0..3)
+                .map(|axis| (v[axis] - center[axis]).powi(2))
+                .sum::<f32>()
+                .sqrt()
+        })
+        .fold(0f32, f32::max);
+    let scale = if radius > 0. {
+        BOARD_RADIUS / radius
+    } else {
+        1.
+    };
+    for v in vertex.iter_mut() {
+        for axis in 0..3 {
+            v[axis] = (v[axis] - center[axis]) * scale;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn edge_shared_by_three_triangles_is_not_manifold() {
+        let indices = [0, 1, 2, 0, 1, 3, 0, 1, 4];
+        assert!(matches!(
+            validate(5, &indices),
+            Err(MeshLoadError::NonManifold {
+                edge: (0, 1),
+                triangles: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn separate_triangles_are_disconnected() {
+        let indices = [0, 1, 2, 3, 4, 5];
+        assert!(matches!(
+            validate(6, &indices),
+            Err(MeshLoadError::Disconnected { components: 2 })
+        ));
+    }
+
+    #[test]
+    fn vertex_only_in_degenerate_triangle_is_dropped() {
+        let vertex = [[0., 0., 0.], [1., 0., 0.], [0., 1., 0.], [0., 0., 1.]];
+        let (vertex, indices) = weld(&vertex, &[0, 1, 2, 0, 0, 3]);
+        assert_eq!(vertex.len(), 3);
+        assert_eq!(indices, [0, 1, 2]);
+        assert!(validate(vertex.len(), &indices).is_ok());
+    }
+
+    #[test]
+    fn fans_sharing_a_vertex_are_not_manifold() {
+        // Two triangles only touching at vertex 0
+        let indices = [0, 1, 2, 0, 3, 4];
+        assert!(matches!(
+            validate(5, &indices),
+            Err(MeshLoadError::NonManifoldVertex { vertex: 0, fans: 2 })
+        ));
+    }
+
+    #[test]
+    fn tetrahedron_is_valid() {
+        let indices = [0, 1, 2, 0, 3, 1, 1, 3, 2, 2, 3, 0];
+        assert!(validate(4, &indices).is_ok());
+    }
+
+    #[test]
+    fn ascii_ply_square_is_split_in_two_triangles() {
+        let ply = "ply
+format ascii 1.0
+element vertex 4
+property float x
+property float y
+property float z
+element face 1
+property list uchar int vertex_indices
+end_header
+0 0 0
+1 0 0
+1 1 0
+0 1 0
+4 0 1 2 3
+";
+        let (vertex, indices) = parse_ply(ply.as_bytes()).unwrap();
+        assert_eq!(
+            vertex,
+            [[0., 0., 0.], [1., 0., 0.], [1., 1., 0.], [0., 1., 0.]]
+        );
+        assert_eq!(indices, [0, 1, 2, 0, 2, 3]);
+    }
+}
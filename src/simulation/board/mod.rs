@@ -1,7 +1,25 @@
+mod board_position;
 mod cell;
+mod graph;
+mod grid;
+mod hex_grid;
+mod hexa_sphere;
 mod ico_sphere;
-mod board_position;
+mod mesh_loader;
+mod obstacle;
+mod pheromone;
+mod terrain;
+mod topology;
 
+pub use board_position::*;
 pub use cell::*;
+pub use graph::*;
+pub use grid::*;
+pub use hex_grid::*;
+pub use hexa_sphere::*;
 pub use ico_sphere::*;
-pub use board_position::*;
+pub use mesh_loader::*;
+pub use obstacle::*;
+pub use pheromone::*;
+pub use terrain::*;
+pub use topology::*;
@@ -0,0 +1,180 @@
+use bevy::prelude::*;
+use image::ImageResult;
+use std::path::Path;
+
+use crate::consts::{ITEM_RADIUS, OBSTACLES, OBSTACLE_MASK};
+use crate::simulation::aco::AcoSolver;
+use crate::simulation::board::{Board, BoardPosition};
+use crate::simulation::state::SimulationState;
+
+/// Colour of the blocked cells
+pub const BLOCKED_CELL_COLOR: Color = Color::DARK_GRAY;
+
+/// Region of the board where ants can't walk and items can't be dropped
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Obstacle {
+    /// Hole made of the cells closer than `radius` to `center`
+    Disc { center: [f32; 3], radius: f32 },
+    /// Wall made of the cells closer than `width / 2` to the plane through the origin
+    /// orthogonal to `normal`, a great circle on the sphere
+    Wall { normal: [f32; 3], width: f32 },
+}
+
+impl Obstacle {
+    pub fn contains(&self, point: Vec3) -> bool {
+        match *self {
+            Obstacle::Disc { center, radius } => point.distance(Vec3::from(center)) < radius,
+            Obstacle::Wall { normal, width } => {
+                point.dot(Vec3::from(normal).normalize()).abs() < width / 2.
+            }
+        }
+    }
+}
+
+/// Marks a blocked cell on boards which don't colour their cells
+#[derive(Component)]
+pub struct ObstacleMarker(pub BoardPosition);
+
+/// Blocks the empty cells inside any of the `obstacles`, returning how many were blocked
+pub fn place_obstacles(board: &mut dyn Board, obstacles: &[Obstacle]) -> usize {
+    let mut blocked = 0;
+    for i in 0..board.size() {
+        let pos = BoardPosition::new(i);
+        let point = Vec3::from(board.world_position(&pos));
+        if !board.is_blocked(&pos)
+            && !board.is_occupied(&pos)
+            && obstacles.iter().any(|obstacle| obstacle.contains(point))
+        {
+            board.set_blocked(&pos, true);
+            blocked += 1;
+        }
+    }
+    blocked
+}
+
+/// Blocks the empty cells lying on the dark pixels of an image, mapped with [`Board::uv`].
+/// Returns how many were blocked
+pub fn load_obstacle_mask(board: &mut dyn Board, path: &Path) -> ImageResult<usize> {
+    let mask = image::open(path)?.into_luma8();
+    let (width, height) = mask.dimensions();
+
+    let mut blocked = 0;
+    for i in 0..board.size() {
+        let pos = BoardPosition::new(i);
+        let [u, v] = board.uv(&pos);
+        let x = ((u * width as f32) as u32).min(width - 1);
+        let y = ((v * height as f32) as u32).min(height - 1);
+        if mask.get_pixel(x, y).0[0] < u8::MAX / 2
+            && !board.is_blocked(&pos)
+            && !board.is_occupied(&pos)
+        {
+            board.set_blocked(&pos, true);
+            blocked += 1;
+        }
+    }
+    Ok(blocked)
+}
+
+/// Blocks the cells given by [`OBSTACLES`] and [`OBSTACLE_MASK`], returning how many were blocked
+pub fn place_configured_obstacles(board: &mut dyn Board) -> ImageResult<usize> {
+    let mut blocked = place_obstacles(board, OBSTACLES);
+    if let Some(path) = OBSTACLE_MASK {
+        blocked += load_obstacle_mask(board, Path::new(path))?;
+    }
+    Ok(blocked)
+}
+
+/// Left click toggles the cell under the cursor between open and blocked,
+/// dragging paints the same value over the cells without items
+pub fn obstacle_paint_input_handler(
+    windows: Res<Windows>,
+    input_mouse: Res<Input<MouseButton>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    mut state: ResMut<SimulationState>,
+    aco: Option<ResMut<AcoSolver>>,
+    mut painting: Local<Option<bool>>,
+) {
+    if !input_mouse.pressed(MouseButton::Left) {
+        *painting = None;
+        return;
+    }
+    let window = windows.get_primary().unwrap();
+    let cursor = match window.cursor_position() {
+        Some(cursor) => cursor,
+        None => return,
+    };
+
+    let (camera, transform) = camera.single();
+    let ndc = cursor / Vec2::new(window.width(), window.height()) * 2. - Vec2::ONE;
+    let ndc_to_world = transform.compute_matrix() * camera.projection_matrix().inverse();
+    let near = ndc_to_world.project_point3(ndc.extend(1.));
+    let far = ndc_to_world.project_point3(ndc.extend(f32::EPSILON));
+
+    let pos = match state.board.graph().ray_cast(near, (far - near).normalize()) {
+        Some(pos) => pos,
+        None => return,
+    };
+    if state.board.is_occupied(&pos) {
+        return;
+    }
+    let blocked = *painting.get_or_insert(!state.board.is_blocked(&pos));
+    if state.board.is_blocked(&pos) != blocked {
+        state.board.set_blocked(&pos, blocked);
+        if let Some(mut aco) = aco {
+            aco.update_obstacles(state.board.as_ref());
+        }
+    }
+}
+
+/// Spawns and despawns the markers of the blocked cells
+pub fn obstacle_sync(
+    mut commands: Commands,
+    state: Res<SimulationState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    markers: Query<(Entity, &ObstacleMarker)>,
+    mut marker_assets: Local<Option<(Handle<Mesh>, Handle<StandardMaterial>)>>,
+) {
+    if !state.is_changed() || state.board.colors_cells() {
+        return;
+    }
+    let (mesh, material) = marker_assets
+        .get_or_insert_with(|| {
+            (
+                meshes.add(Mesh::from(shape::Cube {
+                    size: ITEM_RADIUS * 2.,
+                })),
+                materials.add(BLOCKED_CELL_COLOR.into()),
+            )
+        })
+        .clone();
+
+    let mut marked = vec![false; state.board.size()];
+    for (entity, ObstacleMarker(pos)) in &markers {
+        if state.board.is_blocked(pos) {
+            marked[pos.idx()] = true;
+        } else {
+            commands.entity(entity).despawn();
+        }
+    }
+
+    for (i, marked) in marked.into_iter().enumerate() {
+        let pos = BoardPosition::new(i);
+        if marked || !state.board.is_blocked(&pos) {
+            continue;
+        }
+        let normal = Vec3::from(state.board.normal(&pos));
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform {
+                    translation: Vec3::from(state.board.world_position(&pos)),
+                    rotation: Quat::from_rotation_arc(Vec3::Y, normal),
+                    ..default()
+                },
+                ..default()
+            })
+            .insert(ObstacleMarker(pos));
+    }
+}
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+
+use crate::simulation::board::{Board, BoardPosition};
+
+/// Colour of the cells covered with pheromone, on boards with coloured vertices
+pub const PHEROMONE_COLOR: Color = Color::PURPLE;
+
+/// Smaller amounts are dropped, so diffusing skips the bare cells
+const MIN_PHEROMONE: f32 = 1e-4;
+
+/// How ants lay and follow a [`PheromoneField`]
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct PheromoneConfig {
+    /// Laid on the cell where an item is dropped
+    pub drop_deposit: f32,
+    /// Laid every step on the cell of each ant carrying an item
+    pub carry_deposit: f32,
+    /// Share of the pheromone of a cell spread evenly to its open neighbours every step
+    pub diffusion: f32,
+    /// Share of the pheromone lost every step
+    pub evaporation: f32,
+    /// Single cell moves are weighted by `exp(attraction * pheromone)`
+    pub attraction: f64,
+    /// The drop probability `p` becomes `p + (1 - p) * (1 - exp(-drop_boost * pheromone))`
+    pub drop_boost: f64,
+}
+
+impl PheromoneConfig {
+    pub fn is_enabled(&self) -> bool {
+        self.drop_deposit > 0. || self.carry_deposit > 0.
+    }
+}
+
+/// Pheromone on every cell of the board
+#[derive(Debug, Clone)]
+pub struct PheromoneField {
+    pub config: PheromoneConfig,
+    values: Vec<f32>,
+    /// Values being diffused, kept to avoid allocating every step
+    next: Vec<f32>,
+}
+
+impl PheromoneField {
+    pub fn new(config: PheromoneConfig, size: usize) -> Self {
+        Self {
+            config,
+            values: vec![0.; size],
+            next: vec![0.; size],
+        }
+    }
+
+    /// Clears the field, for a board of `size` cells
+    pub fn reset(&mut self, size: usize) {
+        self.values.clear();
+        self.values.resize(size, 0.);
+        self.next.clear();
+        self.next.resize(size, 0.);
+    }
+
+    pub fn get(&self, pos: &BoardPosition) -> f32 {
+        self.values[pos.idx()]
+    }
+
+    pub fn deposit(&mut self, pos: &BoardPosition, amount: f32) {
+        self.values[pos.idx()] += amount;
+    }
+
+    /// Weight of a move to `pos` following [`PheromoneConfig::attraction`]
+    pub fn attraction(&self, pos: &BoardPosition) -> f64 {
+        (self.config.attraction * self.get(pos) as f64).exp()
+    }
+
+    /// `prob` raised following [`PheromoneConfig::drop_boost`]
+    pub fn boost_drop(&self, pos: &BoardPosition, prob: f64) -> f64 {
+        let boost = 1. - (-self.config.drop_boost * self.get(pos) as f64).exp();
+        prob + (1. - prob) * boost
+    }
+
+    /// Diffuses then evaporates the pheromone, once per step
+    pub fn update(&mut self, board: &dyn Board) {
+        let PheromoneConfig {
+            diffusion,
+            evaporation,
+            ..
+        } = self.config;
+        self.next.iter_mut().for_each(|value| *value = 0.);
+        for (i, &value) in self.values.iter().enumerate() {
+            if value == 0. {
+                continue;
+            }
+            let pos = BoardPosition::new(i);
+            let open = board
+                .adjacent_indices(&pos)
+                .iter()
+                .filter(|&&j| !board.is_blocked(&BoardPosition::new(j as usize)))
+                .count();
+            if open == 0 {
+                self.next[i] += value;
+                continue;
+            }
+            self.next[i] += value * (1. - diffusion);
+            let share = value * diffusion / open as f32;
+            for &j in board.adjacent_indices(&pos) {
+                if !board.is_blocked(&BoardPosition::new(j as usize)) {
+                    self.next[j as usize] += share;
+                }
+            }
+        }
+        for value in &mut self.next {
+            *value *= 1. - evaporation;
+            if *value < MIN_PHEROMONE {
+                *value = 0.;
+            }
+        }
+        std::mem::swap(&mut self.values, &mut self.next);
+    }
+
+    /// Share of the pheromone colour mixed into the cell colour, saturating for large amounts
+    pub fn intensity(&self, pos: &BoardPosition) -> f32 {
+        1. - (-self.get(pos)).exp()
+    }
+}
@@ -0,0 +1,129 @@
+use bevy::prelude::*;
+use image::ImageResult;
+use noise::{Fbm, MultiFractal, NoiseFn, Perlin};
+use rand::{Rng, RngCore};
+
+use crate::consts::{BOARD_RADIUS, TERRAIN, TERRAIN_HEIGHT};
+use crate::simulation::board::{Board, BoardPosition};
+
+/// Height field raising the cells of the board
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Terrain {
+    Flat,
+    /// Fractal Perlin noise, with `frequency` bumps per board radius on the first of the `octaves`
+    Noise {
+        seed: u32,
+        frequency: f64,
+        octaves: usize,
+    },
+    /// Grey levels of an image mapped with [`Board::uv`], white being the highest,
+    /// an equirectangular heightmap on the spheres
+    Heightmap(&'static str),
+}
+
+impl Terrain {
+    /// Elevation of every cell between 0 and `height`, `None` when flat
+    pub fn elevation(&self, board: &dyn Board, height: f32) -> ImageResult<Option<Vec<f32>>> {
+        let samples: Vec<f32> = match *self {
+            Terrain::Flat => return Ok(None),
+            Terrain::Noise {
+                seed,
+                frequency,
+                octaves,
+            } => {
+                let noise = Fbm::<Perlin>::new(seed)
+                    .set_frequency(frequency)
+                    .set_octaves(octaves);
+                (0..board.size())
+                    .map(|i| {
+                        let p =
+                            Vec3::from(board.world_position(&BoardPosition::new(i))) / BOARD_RADIUS;
+                        noise.get([p.x as f64, p.y as f64, p.z as f64]) as f32
+                    })
+                    .collect()
+            }
+            Terrain::Heightmap(path) => {
+                let heightmap = image::open(path)?.into_luma8();
+                let (width, height) = heightmap.dimensions();
+                (0..board.size())
+                    .map(|i| {
+                        let [u, v] = board.uv(&BoardPosition::new(i));
+                        let x = ((u * width as f32) as u32).min(width - 1);
+                        let y = ((v * height as f32) as u32).min(height - 1);
+                        heightmap.get_pixel(x, y).0[0] as f32
+                    })
+                    .collect()
+            }
+        };
+
+        let min = samples.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = samples.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+        let range = if max > min { max - min } else { 1. };
+        Ok(Some(
+            samples
+                .into_iter()
+                .map(|sample| (sample - min) / range * height)
+                .collect(),
+        ))
+    }
+}
+
+/// How the slope between two cells biases the moves of the ants
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct TerrainMovement {
+    /// Moves are weighted by `exp(-downhill_preference * slope)`, zero being indifferent
+    pub downhill_preference: f64,
+    /// Rise over distance above which a move, up or down, is steep
+    pub steep_slope: f64,
+    /// Probability of staying instead of taking a steep move
+    pub steep_rejection: f64,
+}
+
+impl TerrainMovement {
+    fn slope(board: &dyn Board, from: &BoardPosition, to: &BoardPosition) -> f64 {
+        let rise = board.elevation(to) - board.elevation(from);
+        let distance = Vec3::from(board.offset(from, to)).length();
+        (rise / distance) as f64
+    }
+
+    /// Relative chance of moving from `from` to `to`, higher downhill
+    pub fn weight(&self, board: &dyn Board, from: &BoardPosition, to: &BoardPosition) -> f64 {
+        (-self.downhill_preference * Self::slope(board, from, to)).exp()
+    }
+
+    /// Whether a move from `from` to `to` is steep and gets rejected
+    pub fn rejects(
+        &self,
+        board: &dyn Board,
+        from: &BoardPosition,
+        to: &BoardPosition,
+        rng: &mut dyn RngCore,
+    ) -> bool {
+        Self::slope(board, from, to).abs() > self.steep_slope && rng.gen_bool(self.steep_rejection)
+    }
+
+    /// Random open neighbour favouring the downhill ones, or `pos` itself
+    /// when the chosen move is steep and gets rejected
+    pub fn get_adjacent(
+        &self,
+        board: &dyn Board,
+        pos: &BoardPosition,
+        rng: &mut dyn RngCore,
+    ) -> BoardPosition {
+        let next =
+            board.get_weighted_adjacent(pos, &|adjacent| self.weight(board, pos, adjacent), rng);
+        if self.rejects(board, pos, &next, rng) {
+            *pos
+        } else {
+            next
+        }
+    }
+}
+
+/// Raises the cells following [`TERRAIN`], up to [`TERRAIN_HEIGHT`]
+pub fn place_configured_terrain(board: &mut dyn Board) -> ImageResult<()> {
+    if let Some(elevation) = TERRAIN.elevation(board, TERRAIN_HEIGHT)? {
+        board.set_elevation(elevation);
+    }
+    Ok(())
+}
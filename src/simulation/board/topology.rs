@@ -0,0 +1,406 @@
+use bevy::prelude::*;
+use bevy::render::mesh::VertexAttributeValues;
+use rand::RngCore;
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+
+use crate::consts::{BOARD_RADIUS, BOARD_SUBDIVISIONS, FORAGING, GRID_SIZE, SEED, VISION_RADIUS};
+use crate::simulation::aco::AcoSolver;
+use crate::simulation::ant::MovementModel;
+use crate::simulation::board::{
+    place_configured_obstacles, place_configured_terrain, BoardGraph, BoardPosition, Cell, HexGrid,
+    HexaSphereBoard, IcoBoard, MeshLoadError, ObstacleMarker, SquareGrid, BLOCKED_CELL_COLOR,
+    EMPTY_CELL_COLOR, PHEROMONE_COLOR,
+};
+use crate::simulation::item::item_color;
+use crate::simulation::state::{Respawn, SimulationState};
+
+#[derive(Component)]
+pub struct BoardMesh;
+
+/// Surface the ants walk on.
+/// Topologies only differ in how the cells are laid out, the cells themselves live in a [`BoardGraph`]
+pub trait Board: Send + Sync {
+    fn graph(&self) -> &BoardGraph;
+
+    fn graph_mut(&mut self) -> &mut BoardGraph;
+
+    /// Unit vector pointing out of the board surface at `pos`
+    fn normal(&self, pos: &BoardPosition) -> [f32; 3];
+
+    /// Mesh rendered under the cells
+    fn mesh(&self) -> Mesh;
+
+    /// Vertices of [`Board::mesh`] drawing the cell at `pos`, on boards where each cell is its own polygon.
+    /// Those boards show the items by colouring the cells instead of drawing them
+    fn cell_vertices(&self, _pos: &BoardPosition) -> Option<Range<usize>> {
+        None
+    }
+
+    fn colors_cells(&self) -> bool {
+        self.cell_vertices(&BoardPosition::new(0)).is_some()
+    }
+
+    /// Vertices of [`Board::mesh`] taking the colour of the cell at `pos`, used to show the pheromone.
+    /// Only boards drawing each cell or whose vertices are the cells have them
+    fn colored_vertices(&self, pos: &BoardPosition) -> Option<Range<usize>> {
+        self.cell_vertices(pos)
+    }
+
+    fn has_vertex_colors(&self) -> bool {
+        self.colored_vertices(&BoardPosition::new(0)).is_some()
+    }
+
+    fn size(&self) -> usize {
+        self.graph().size()
+    }
+
+    fn world_position(&self, pos: &BoardPosition) -> [f32; 3] {
+        self.graph().world_position(pos)
+    }
+
+    /// Vector from the cell `from` to the cell `to`, along the shortest way on boards wrapping around
+    fn offset(&self, from: &BoardPosition, to: &BoardPosition) -> [f32; 3] {
+        (Vec3::from(self.world_position(to)) - Vec3::from(self.world_position(from))).into()
+    }
+
+    fn adjacent_indices(&self, pos: &BoardPosition) -> &[u32] {
+        self.graph().adjacent_indices(pos)
+    }
+
+    fn new_random_position(&self, rng: &mut dyn RngCore) -> BoardPosition {
+        self.graph().new_random_position(rng)
+    }
+
+    fn get_random_adjacent(&self, pos: &BoardPosition, rng: &mut dyn RngCore) -> BoardPosition {
+        self.graph().get_random_adjacent(pos, rng)
+    }
+
+    fn get_weighted_adjacent(
+        &self,
+        pos: &BoardPosition,
+        weight: &dyn Fn(&BoardPosition) -> f64,
+        rng: &mut dyn RngCore,
+    ) -> BoardPosition {
+        self.graph().get_weighted_adjacent(pos, weight, rng)
+    }
+
+    fn get_cell(&self, pos: &BoardPosition) -> &Cell {
+        self.graph().get_cell(pos)
+    }
+
+    fn place_item(&mut self, pos: &BoardPosition, item: usize, kind: usize) {
+        self.graph_mut().place_item(pos, item, kind)
+    }
+
+    fn take_item(&mut self, pos: &BoardPosition) -> Option<usize> {
+        self.graph_mut().take_item(pos)
+    }
+
+    fn remove_item(&mut self, pos: &BoardPosition, item: usize) -> bool {
+        self.graph_mut().remove_item(pos, item)
+    }
+
+    fn is_occupied(&self, pos: &BoardPosition) -> bool {
+        self.graph().is_occupied(pos)
+    }
+
+    fn is_full(&self, pos: &BoardPosition) -> bool {
+        self.graph().is_full(pos)
+    }
+
+    fn is_blocked(&self, pos: &BoardPosition) -> bool {
+        self.graph().is_blocked(pos)
+    }
+
+    fn set_blocked(&mut self, pos: &BoardPosition, blocked: bool) {
+        self.graph_mut().set_blocked(pos, blocked)
+    }
+
+    fn open_count(&self) -> usize {
+        self.graph().open_count()
+    }
+
+    fn vision_count(&self, pos: &BoardPosition) -> usize {
+        self.graph().vision_count(pos)
+    }
+
+    fn items_in_vision(&self, pos: &BoardPosition) -> usize {
+        self.graph().items_in_vision(pos)
+    }
+
+    fn has_terrain(&self) -> bool {
+        self.graph().has_terrain()
+    }
+
+    fn elevation(&self, pos: &BoardPosition) -> f32 {
+        self.graph().elevation(pos)
+    }
+
+    /// Sets the height of every cell. Flat boards keep their cells in the plane,
+    /// the terrain only biasing the moves of the ants
+    fn set_elevation(&mut self, elevation: Vec<f32>) {
+        self.graph_mut().set_elevation(elevation, None)
+    }
+
+    fn item_density(&self, pos: &BoardPosition) -> f64 {
+        self.graph().item_density(pos)
+    }
+
+    /// Texture coordinates of the cell, used to map images onto the board.
+    /// Equirectangular around the Y axis by default, with `v` growing from the north pole
+    fn uv(&self, pos: &BoardPosition) -> [f32; 2] {
+        let p = Vec3::from(self.world_position(pos)).normalize_or_zero();
+        [
+            0.5 + p.x.atan2(p.z) / std::f32::consts::TAU,
+            p.y.clamp(-1., 1.).acos() / std::f32::consts::PI,
+        ]
+    }
+}
+
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub enum BoardTopology {
+    #[default]
+    IcoSphere,
+    /// Dual of the icosphere, with a pentagon or hexagon per cell
+    HexaSphere,
+    /// Flat square grid, with 8 neighbours per cell
+    Square,
+    /// Square grid wrapping around its edges, as in Lumer and Faieta
+    Torus,
+    /// Flat grid of hexagonal cells
+    Hex,
+    /// Vertices of a triangle mesh loaded from an OBJ, PLY or glTF file
+    Mesh(PathBuf),
+}
+
+/// Subdivisions of the spheres or cells per side of the flat boards, loaded meshes have a fixed one
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct BoardResolution(pub usize);
+
+/// Request to rebuild the board at another resolution
+#[derive(Debug, Copy, Clone)]
+pub struct BoardRebuildEvent {
+    pub resolution: BoardResolution,
+    pub respawn: Respawn,
+}
+
+impl BoardTopology {
+    /// Only loading a mesh can fail
+    pub fn build(&self, vision_radius: usize) -> Result<Box<dyn Board>, MeshLoadError> {
+        self.build_at(self.default_resolution(), vision_radius)
+    }
+
+    pub fn build_at(
+        &self,
+        resolution: BoardResolution,
+        vision_radius: usize,
+    ) -> Result<Box<dyn Board>, MeshLoadError> {
+        let BoardResolution(resolution) = resolution;
+        Ok(match self {
+            BoardTopology::IcoSphere => Box::new(IcoBoard::from_mesh(
+                &Mesh::from(shape::Icosphere {
+                    radius: BOARD_RADIUS,
+                    subdivisions: resolution,
+                }),
+                vision_radius,
+            )),
+            BoardTopology::HexaSphere => Box::new(HexaSphereBoard::new(resolution, vision_radius)),
+            BoardTopology::Square => Box::new(SquareGrid::new(
+                resolution,
+                resolution,
+                false,
+                vision_radius,
+            )),
+            BoardTopology::Torus => {
+                Box::new(SquareGrid::new(resolution, resolution, true, vision_radius))
+            }
+            BoardTopology::Hex => Box::new(HexGrid::new(resolution, resolution, vision_radius)),
+            BoardTopology::Mesh(path) => Box::new(IcoBoard::load(path, vision_radius)?),
+        })
+    }
+
+    pub fn default_resolution(&self) -> BoardResolution {
+        match self {
+            BoardTopology::IcoSphere | BoardTopology::HexaSphere | BoardTopology::Mesh(_) => {
+                BoardResolution(BOARD_SUBDIVISIONS)
+            }
+            BoardTopology::Square | BoardTopology::Torus | BoardTopology::Hex => {
+                BoardResolution(GRID_SIZE)
+            }
+        }
+    }
+
+    pub fn is_resizable(&self) -> bool {
+        !matches!(self, BoardTopology::Mesh(_))
+    }
+
+    /// Builds the board and places the configured obstacles and terrain on it
+    pub fn build_configured(&self, resolution: BoardResolution) -> Result<Box<dyn Board>, String> {
+        let mut board = self
+            .build_at(resolution, VISION_RADIUS)
+            .map_err(|e| format!("Could not build {self:?} board: {e}"))?;
+        place_configured_obstacles(board.as_mut())
+            .map_err(|e| format!("Could not load the obstacle mask: {e}"))?;
+        place_configured_terrain(board.as_mut())
+            .map_err(|e| format!("Could not load the heightmap: {e}"))?;
+        Ok(board)
+    }
+}
+
+impl FromStr for BoardTopology {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "icosphere" => Ok(BoardTopology::IcoSphere),
+            "hexasphere" => Ok(BoardTopology::HexaSphere),
+            "square" => Ok(BoardTopology::Square),
+            "torus" => Ok(BoardTopology::Torus),
+            "hex" => Ok(BoardTopology::Hex),
+            _ if Path::new(s).extension().is_some() => Ok(BoardTopology::Mesh(PathBuf::from(s))),
+            _ => Err(format!(
+                "Unknown board topology {s}, expected one of icosphere, hexasphere, square, torus, hex or a mesh file"
+            )),
+        }
+    }
+}
+
+pub fn board_setup(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    topology: Res<BoardTopology>,
+    resolution: Res<BoardResolution>,
+    movement: Option<Res<MovementModel>>,
+) {
+    let board = topology
+        .build_configured(*resolution)
+        .unwrap_or_else(|error_message| {
+            error!("{error_message}");
+            panic!("{error_message}");
+        });
+
+    info!(
+        "{:?} board with {} positions, {} blocked!",
+        *topology,
+        board.size(),
+        board.size() - board.open_count()
+    );
+
+    let mesh_handle = meshes.add(board.mesh());
+    // Vertex colours are multiplied by the base colour
+    let color = if board.has_vertex_colors() {
+        Color::WHITE
+    } else {
+        Color::GREEN
+    };
+
+    let mut state = SimulationState::new(board, SEED);
+    if let Some(movement) = movement {
+        state.set_movement(*movement);
+    }
+    if let Some(config) = FORAGING {
+        state.start_foraging(config);
+    }
+    commands.insert_resource(state);
+
+    commands
+        .spawn_bundle(PbrBundle {
+            mesh: mesh_handle,
+            material: materials.add(color.into()),
+            ..default()
+        })
+        .insert(BoardMesh);
+}
+
+/// Colours the cells of the boards with vertex colours, grey when blocked, in the colour of the item kind
+/// when holding an item on boards drawing each cell and tinted by the pheromone otherwise
+pub fn board_cell_color_update(
+    state: Res<SimulationState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    query: Query<&Handle<Mesh>, With<BoardMesh>>,
+) {
+    if !state.is_changed() || !state.board.has_vertex_colors() {
+        return;
+    }
+    let mesh = meshes.get_mut(query.single()).unwrap();
+    let colors = match mesh.attribute_mut(Mesh::ATTRIBUTE_COLOR).unwrap() {
+        VertexAttributeValues::Float32x4(v) => v,
+        _ => panic!("Unexpected Attribute Format"),
+    };
+
+    let (empty, blocked) = (
+        Vec4::from(EMPTY_CELL_COLOR.as_rgba_f32()),
+        Vec4::from(BLOCKED_CELL_COLOR.as_rgba_f32()),
+    );
+    let pheromone = Vec4::from(PHEROMONE_COLOR.as_rgba_f32());
+    let colors_cells = state.board.colors_cells();
+    for i in 0..state.board.size() {
+        let pos = BoardPosition::new(i);
+        let color = if state.board.is_blocked(&pos) {
+            blocked
+        } else if let Some(&item) = state
+            .board
+            .get_cell(&pos)
+            .food
+            .last()
+            .filter(|_| colors_cells)
+        {
+            Vec4::from(item_color(state.items[item].kind).as_rgba_f32())
+        } else {
+            empty.lerp(pheromone, state.pheromone.intensity(&pos))
+        }
+        .into();
+        for vertex in state.board.colored_vertices(&pos).unwrap() {
+            colors[vertex] = color;
+        }
+    }
+}
+
+/// Replaces the board by one at the requested resolution, keeping the old one when it fails
+#[allow(clippy::too_many_arguments)]
+pub fn board_rebuild(
+    mut commands: Commands,
+    mut events: EventReader<BoardRebuildEvent>,
+    topology: Res<BoardTopology>,
+    mut resolution: ResMut<BoardResolution>,
+    mut state: ResMut<SimulationState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    board_mesh: Query<&Handle<Mesh>, With<BoardMesh>>,
+    markers: Query<Entity, With<ObstacleMarker>>,
+    aco: Option<ResMut<AcoSolver>>,
+) {
+    let event = match events.iter().last() {
+        Some(event) => *event,
+        None => return,
+    };
+
+    let board = match topology.build_configured(event.resolution) {
+        Ok(board) => board,
+        Err(error_message) => {
+            error!("{error_message}");
+            return;
+        }
+    };
+    let mesh = board.mesh();
+    let size = board.size();
+    if let Err(error_message) = state.rebuild(board, event.respawn) {
+        error!("{error_message}");
+        return;
+    }
+    *resolution = event.resolution;
+    info!(
+        "Rebuilt {:?} board at resolution {} with {} positions",
+        *topology, resolution.0, size
+    );
+
+    *meshes.get_mut(board_mesh.single()).unwrap() = mesh;
+    for entity in &markers {
+        commands.entity(entity).despawn();
+    }
+    if let Some(mut aco) = aco {
+        *aco = AcoSolver::new(aco.config, state.board.as_ref(), SEED);
+    }
+}
@@ -0,0 +1,151 @@
+//! Several colonies sharing the board, each with its own ants and behaviour
+
+use bevy::prelude::Color;
+use rand::seq::SliceRandom;
+use rand::RngCore;
+use std::str::FromStr;
+
+use crate::simulation::ant::{AntParameterConfig, MovementModel, ProbabilityModel};
+use crate::simulation::board::{Board, BoardPosition};
+use crate::simulation::state::ItemState;
+
+/// Where the ants drop what they carry
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum DropPolicy {
+    /// Their own cell, when it isn't full. Ants unloading several items
+    /// go on with a random neighbour once it is
+    #[default]
+    OwnCell,
+    /// A random neighbour which isn't full
+    RandomNeighbour,
+    /// The neighbour which isn't full with the highest item density
+    DensestNeighbour,
+}
+
+impl FromStr for DropPolicy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "own" => Ok(DropPolicy::OwnCell),
+            "random" => Ok(DropPolicy::RandomNeighbour),
+            "densest" => Ok(DropPolicy::DensestNeighbour),
+            _ => Err(format!(
+                "Unknown drop policy {s}, expected one of own, random or densest"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ColonyConfig {
+    /// Shown in the diagnostics and in the headless columns
+    pub name: &'static str,
+    /// Ants spawned at startup
+    pub count: usize,
+    pub color: Color,
+    pub vision_radius: usize,
+    pub movement: MovementModel,
+    pub probability: ProbabilityModel,
+    /// Items each ant carries at most
+    pub capacity: usize,
+    pub drop_policy: DropPolicy,
+    /// Drawn for every ant when it spawns
+    pub parameters: AntParameterConfig,
+    /// Item kinds the ants see and pick up, every kind when `None`
+    pub kinds: Option<&'static [usize]>,
+}
+
+impl ColonyConfig {
+    pub fn handles(&self, kind: usize) -> bool {
+        match self.kinds {
+            Some(kinds) => kinds.contains(&kind),
+            None => true,
+        }
+    }
+
+    /// Whether the ants pick up the item on top of the stack at `pos`, if any
+    pub fn handles_top(&self, board: &dyn Board, items: &[ItemState], pos: &BoardPosition) -> bool {
+        matches!(board.get_cell(pos).food.last(), Some(&item) if self.handles(items[item].kind))
+    }
+
+    /// Ratio of the room of the open cells within the vision radius of `pos` taken by items
+    /// the colony handles
+    pub fn item_density(&self, board: &dyn Board, pos: &BoardPosition) -> f64 {
+        self.item_density_within(board, pos, self.vision_radius)
+    }
+
+    /// Item density seen by an ant of the colony with its own vision `radius`
+    pub fn item_density_within(
+        &self,
+        board: &dyn Board,
+        pos: &BoardPosition,
+        radius: usize,
+    ) -> f64 {
+        board.graph().item_density_within(pos, radius, self.kinds)
+    }
+
+    /// Whether an ant at `pos` has a cell to drop an item on, following the drop policy
+    pub fn can_drop(&self, board: &dyn Board, pos: &BoardPosition) -> bool {
+        match self.drop_policy {
+            DropPolicy::OwnCell => is_free(board, pos),
+            DropPolicy::RandomNeighbour | DropPolicy::DensestNeighbour => board
+                .graph()
+                .get_all_adjacent(pos)
+                .any(|pos| is_free(board, &pos)),
+        }
+    }
+
+    /// Cell where an ant at `pos` seeing `vision_radius` cells far drops an item
+    /// following the drop policy, if any
+    pub fn drop_target(
+        &self,
+        board: &dyn Board,
+        pos: &BoardPosition,
+        vision_radius: usize,
+        unloading: bool,
+        rng: &mut dyn RngCore,
+    ) -> Option<BoardPosition> {
+        let free = || {
+            board
+                .graph()
+                .get_all_adjacent(pos)
+                .filter(|pos| is_free(board, pos))
+        };
+        match self.drop_policy {
+            DropPolicy::OwnCell if is_free(board, pos) => Some(*pos),
+            DropPolicy::OwnCell if !unloading => None,
+            DropPolicy::OwnCell | DropPolicy::RandomNeighbour => {
+                free().collect::<Vec<_>>().choose(rng).copied()
+            }
+            DropPolicy::DensestNeighbour => free()
+                .map(|pos| (pos, self.item_density_within(board, &pos, vision_radius)))
+                .max_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(pos, _)| pos),
+        }
+    }
+}
+
+/// Whether an item can be dropped on the cell
+fn is_free(board: &dyn Board, pos: &BoardPosition) -> bool {
+    !board.is_full(pos) && !board.is_blocked(pos)
+}
+
+#[derive(Debug, Clone)]
+pub struct Colony {
+    pub config: ColonyConfig,
+    /// Items picked up since the start
+    pub pickups: usize,
+    /// Items dropped, or delivered when foraging, since the start
+    pub drops: usize,
+}
+
+impl Colony {
+    pub fn new(config: ColonyConfig) -> Self {
+        Self {
+            config,
+            pickups: 0,
+            drops: 0,
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use crate::simulation::board::BoardSphere;
+use crate::simulation::board::{BoardMesh, BoardRebuildEvent, BoardResolution, BoardTopology};
+use crate::simulation::state::Respawn;
 use bevy::pbr::wireframe::Wireframe;
 use bevy::prelude::*;
 
@@ -33,7 +34,7 @@ pub fn simulation_ending_input_handler(
 pub fn wireframe_input_handler(
     kbd: Res<Input<KeyCode>>,
     mut commands: Commands,
-    mut query: Query<(Entity, Option<&mut Wireframe>), With<BoardSphere>>,
+    mut query: Query<(Entity, Option<&mut Wireframe>), With<BoardMesh>>,
 ) {
     if kbd.just_pressed(KeyCode::W) {
         let (entity, wireframe) = query.single_mut();
@@ -43,3 +44,34 @@ pub fn wireframe_input_handler(
         };
     }
 }
+
+/// `]` doubles and `[` halves the board resolution, moving ants and items to the closest cells.
+/// Holding shift respawns them at random instead
+pub fn board_resolution_input_handler(
+    kbd: Res<Input<KeyCode>>,
+    topology: Res<BoardTopology>,
+    resolution: Res<BoardResolution>,
+    mut events: EventWriter<BoardRebuildEvent>,
+) {
+    let BoardResolution(current) = *resolution;
+    let new = if kbd.just_pressed(KeyCode::RBracket) {
+        current * 2
+    } else if kbd.just_pressed(KeyCode::LBracket) {
+        (current / 2).max(1)
+    } else {
+        return;
+    };
+    if !topology.is_resizable() || new == current {
+        return;
+    }
+
+    let respawn = if kbd.pressed(KeyCode::LShift) || kbd.pressed(KeyCode::RShift) {
+        Respawn::Fresh
+    } else {
+        Respawn::Closest
+    };
+    events.send(BoardRebuildEvent {
+        resolution: BoardResolution(new),
+        respawn,
+    });
+}
@@ -0,0 +1,61 @@
+//! Ants getting in each other's way: a cell only holds so many of them,
+//! and ants of the same colony meeting on a cell share what they carry or remember
+
+/// What an ant does when the cell it walked to is already crowded
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum BlockedMove {
+    /// Stays where it started the step
+    Wait,
+    /// Tries this many random neighbours of where it started, staying there if all are crowded
+    Retry(usize),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct ExclusionConfig {
+    /// Ants a cell holds at most, nests excepted. Ants placed together at startup may go beyond
+    pub max_ants: usize,
+    pub blocked: BlockedMove,
+}
+
+/// What two ants of the same colony ending their step on the same cell exchange
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Exchange {
+    /// The ant carrying more items hands one over to the other
+    Items,
+    /// Each ant remembers the cells the other remembers
+    Memory,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct InteractionConfig {
+    pub exchange: Exchange,
+    /// Chance of two ants meeting to exchange
+    pub probability: f64,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct CrowdingConfig {
+    /// Any number of ants share a cell when `None`
+    pub exclusion: Option<ExclusionConfig>,
+    /// Ants ignore each other when `None`
+    pub interaction: Option<InteractionConfig>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Crowding {
+    pub config: CrowdingConfig,
+    /// Moves ending on a crowded cell since the start, retried or not
+    pub blocked: usize,
+    /// Exchanges between ants since the start
+    pub exchanges: usize,
+}
+
+impl Crowding {
+    pub fn new(config: CrowdingConfig) -> Self {
+        Self {
+            config,
+            blocked: 0,
+            exchanges: 0,
+        }
+    }
+}
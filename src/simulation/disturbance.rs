@@ -0,0 +1,111 @@
+//! Continuous disturbance: items keep appearing and disappearing during the run,
+//! so the clusters reach a steady state instead of settling for good
+
+use bevy::prelude::Vec3;
+use rand::{Rng, RngCore};
+
+use crate::simulation::board::{Board, BoardPosition};
+
+/// Where the items of a source appear
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SourceLocation {
+    /// Random open cell which isn't full
+    Random,
+    /// Cell with room closest to one of these points, picked at random
+    Points(&'static [[f32; 3]]),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ItemSource {
+    /// Items added every step, on average
+    pub rate: f64,
+    pub location: SourceLocation,
+    /// Kind of the added items, cycling through the kinds like the first ones when `None`
+    pub kind: Option<usize>,
+}
+
+impl ItemSource {
+    /// Number of items added this step, `rate` on average
+    pub fn count(&self, rng: &mut dyn RngCore) -> usize {
+        let whole = self.rate.floor();
+        whole as usize + rng.gen_bool(self.rate - whole) as usize
+    }
+
+    /// Cell where an item appears, `None` when there is no room left.
+    /// `allowed` rules out the cells which can't take any item, such as the nests
+    pub fn position(
+        &self,
+        board: &dyn Board,
+        allowed: &dyn Fn(&BoardPosition) -> bool,
+        rng: &mut dyn RngCore,
+    ) -> Option<BoardPosition> {
+        let graph = board.graph();
+        let is_free =
+            |pos: &BoardPosition| !graph.is_blocked(pos) && !graph.is_full(pos) && allowed(pos);
+        match self.location {
+            SourceLocation::Points(points) if !points.is_empty() => {
+                let point = Vec3::from(points[rng.gen_range(0..points.len())]);
+                let closest = graph.closest_cell(point, BoardPosition::default());
+                graph.find_nearby(closest, is_free)
+            }
+            // A few random tries before looking for the free cells, which is slower
+            _ => (0..32)
+                .map(|_| board.new_random_position(rng))
+                .find(is_free)
+                .or_else(|| {
+                    let free: Vec<_> = (0..graph.size())
+                        .map(BoardPosition::new)
+                        .filter(is_free)
+                        .collect();
+                    (!free.is_empty()).then(|| free[rng.gen_range(0..free.len())])
+                }),
+        }
+    }
+}
+
+/// Area of the board where the items lying there disappear
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct RemovalZone {
+    pub center: [f32; 3],
+    /// Distance to the center of the cells in the zone
+    pub radius: f32,
+    /// Chance of every item in the zone to be removed each step
+    pub rate: f64,
+}
+
+impl RemovalZone {
+    pub fn contains(&self, board: &dyn Board, pos: &BoardPosition) -> bool {
+        Vec3::from(board.world_position(pos)).distance(Vec3::from(self.center)) <= self.radius
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct DisturbanceConfig {
+    pub sources: &'static [ItemSource],
+    /// Steps an item lasts on the board, counted again every time an ant drops it.
+    /// Items never decay when `None`
+    pub decay: Option<u64>,
+    pub zones: &'static [RemovalZone],
+}
+
+#[derive(Debug, Clone)]
+pub struct Disturbance {
+    pub config: DisturbanceConfig,
+    /// Items added by the sources since the start
+    pub added: usize,
+    /// Items decayed since the start
+    pub decayed: usize,
+    /// Items removed in the zones since the start
+    pub removed: usize,
+}
+
+impl Disturbance {
+    pub fn new(config: DisturbanceConfig) -> Self {
+        Self {
+            config,
+            added: 0,
+            decayed: 0,
+            removed: 0,
+        }
+    }
+}
@@ -0,0 +1,129 @@
+//! Nest and forage mode: ants search the board for food, bring it back to a nest
+//! and lay a trail on their way home which the searching ants follow
+
+use bevy::log::error;
+use bevy::prelude::*;
+
+use crate::consts::ITEM_RADIUS;
+use crate::simulation::board::{Board, BoardPosition, PheromoneConfig};
+use crate::simulation::state::SimulationState;
+
+/// Colour of the rings marking the nests
+pub const NEST_COLOR: Color = Color::ORANGE;
+
+/// Food piled on the cells closest to `center`
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct FoodSource {
+    pub center: [f32; 3],
+    pub quantity: usize,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct ForagingConfig {
+    /// Each nest is the open cell closest to one of these points, the ants start there
+    pub nests: &'static [[f32; 3]],
+    pub sources: &'static [FoodSource],
+    /// Ants carrying food favour the neighbours towards the closest nest,
+    /// weighted by `exp(homing * cos(angle))`
+    pub homing: f64,
+    /// Laid by the ants carrying food, replacing the sorting pheromone
+    pub trail: PheromoneConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct Foraging {
+    pub config: ForagingConfig,
+    pub nests: Vec<BoardPosition>,
+    /// Food brought back to a nest since the start
+    pub delivered: usize,
+    /// Food brought back during the last step
+    pub delivered_last_step: usize,
+}
+
+impl Foraging {
+    /// Finds the nests on `board`
+    pub fn new(config: ForagingConfig, board: &dyn Board) -> Self {
+        if config.nests.is_empty() {
+            let error_message = "Foraging needs at least one nest";
+            error!("{error_message}");
+            panic!("{error_message}");
+        }
+        let graph = board.graph();
+        let nests = config
+            .nests
+            .iter()
+            .map(|&point| {
+                let closest = graph.closest_cell(Vec3::from(point), BoardPosition::default());
+                graph
+                    .find_nearby(closest, |pos| !graph.is_blocked(pos))
+                    .unwrap()
+            })
+            .collect();
+        Self {
+            config,
+            nests,
+            delivered: 0,
+            delivered_last_step: 0,
+        }
+    }
+
+    pub fn is_nest(&self, pos: &BoardPosition) -> bool {
+        self.nests.contains(pos)
+    }
+
+    pub fn closest_nest(&self, board: &dyn Board, pos: &BoardPosition) -> BoardPosition {
+        let distance = |nest: &BoardPosition| Vec3::from(board.offset(pos, nest)).length();
+        *self
+            .nests
+            .iter()
+            .min_by(|a, b| distance(a).total_cmp(&distance(b)))
+            .unwrap()
+    }
+}
+
+/// Ring around a nest, mirroring [`Foraging::nests`]`[i]`
+#[derive(Component)]
+pub struct NestMarker(pub usize);
+
+pub fn nest_spawn(
+    mut commands: Commands,
+    state: Res<SimulationState>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+) {
+    let foraging = match &state.foraging {
+        Some(foraging) => foraging,
+        None => return,
+    };
+    let mesh = meshes.add(Mesh::from(shape::Torus {
+        radius: ITEM_RADIUS * 2.,
+        ring_radius: ITEM_RADIUS / 2.,
+        ..default()
+    }));
+    let material = materials.add(NEST_COLOR.into());
+    for i in 0..foraging.nests.len() {
+        commands
+            .spawn_bundle(PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                ..default()
+            })
+            .insert(NestMarker(i));
+    }
+}
+
+/// Moves the nest rings onto their cells, which change when the board is rebuilt
+pub fn nest_sync(state: Res<SimulationState>, mut query: Query<(&NestMarker, &mut Transform)>) {
+    let foraging = match &state.foraging {
+        Some(foraging) if state.is_changed() => foraging,
+        _ => return,
+    };
+    for (NestMarker(i), mut transform) in &mut query {
+        let pos = foraging.nests[*i];
+        let translation = Vec3::from(state.board.world_position(&pos));
+        if transform.translation != translation {
+            transform.translation = translation;
+            transform.rotation = Quat::from_rotation_arc(Vec3::Y, state.board.normal(&pos).into());
+        }
+    }
+}
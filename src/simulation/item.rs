@@ -1,83 +1,171 @@
 use bevy::prelude::*;
 
 use crate::consts::{ITEM_COUNT, ITEM_RADIUS, ITEM_SUBDIVISIONS};
-use crate::{BoardPosition, IcoBoard};
+use crate::simulation::board::BoardPosition;
+use crate::simulation::state::SimulationState;
 
+/// Colours of the item kinds, repeating when there are more kinds
+pub const ITEM_COLORS: [Color; 6] = [
+    Color::RED,
+    Color::BLUE,
+    Color::YELLOW,
+    Color::CYAN,
+    Color::FUCHSIA,
+    Color::LIME_GREEN,
+];
+
+pub fn item_color(kind: usize) -> Color {
+    ITEM_COLORS[kind % ITEM_COLORS.len()]
+}
+
+/// Where the item `id` lying at `pos` is drawn, above the ones under it in the stack
+fn item_translation(state: &SimulationState, id: usize, pos: &BoardPosition) -> Vec3 {
+    let level = state
+        .board
+        .get_cell(pos)
+        .food
+        .iter()
+        .position(|&item| item == id)
+        .unwrap_or_default();
+    Vec3::from(state.board.world_position(pos))
+        + Vec3::from(state.board.normal(pos)) * ITEM_RADIUS * 2. * level as f32
+}
+
+/// Rendered item, mirroring [`SimulationState::items`]`[id]`
 #[derive(Component)]
-pub struct Item;
+pub struct Item {
+    pub id: usize,
+    pub kind: usize,
+}
+
+/// Mesh and kind materials of the items, kept to draw the items added during the run
+pub struct ItemAssets {
+    mesh: Handle<Mesh>,
+    kind_materials: Vec<Handle<StandardMaterial>>,
+}
 
 pub fn item_spawn(
     mut commands: Commands,
-    mut board: ResMut<IcoBoard>,
+    mut state: ResMut<SimulationState>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    if ITEM_COUNT > board.size() {
-        let error_message = format!(
-            "More food ({}) than board cells ({})",
-            ITEM_COUNT,
-            board.size()
-        );
-        error!("{error_message}");
-        panic!("{error_message}");
+    let items = if state.foraging.is_some() {
+        state.spawn_food_sources()
+    } else {
+        state.spawn_items(ITEM_COUNT)
+    };
+    // Boards drawing each cell show the items by colouring the cells
+    if state.board.colors_cells() {
+        return;
     }
 
-    let item_mesh = meshes.add(Mesh::from(shape::Icosphere {
-        radius: ITEM_RADIUS,
-        subdivisions: ITEM_SUBDIVISIONS,
-    }));
-    let item_material = materials.add(Color::RED.into());
-
-    for _ in 0..ITEM_COUNT {
-        let pos = loop {
-            let pos = board.new_random_position();
-            if board.get_cell(&pos).food.is_none() {
-                break pos;
-            }
-        };
-
-        let id = commands
-            .spawn()
-            .insert_bundle(PbrBundle {
-                mesh: item_mesh.clone(),
-                material: item_material.clone(),
-                transform: Transform {
-                    translation: Vec3::from(board.world_position(&pos)),
-                    ..default()
-                },
-                ..default()
-            })
-            .insert(Item)
-            .insert(pos)
-            .id();
-
-        board.get_cell_mut(&pos).food = Some(id);
+    let assets = ItemAssets {
+        mesh: meshes.add(Mesh::from(shape::Icosphere {
+            radius: ITEM_RADIUS,
+            subdivisions: ITEM_SUBDIVISIONS,
+        })),
+        kind_materials: (0..ITEM_COLORS.len())
+            .map(|kind| materials.add(item_color(kind).into()))
+            .collect(),
+    };
+    for id in items {
+        spawn_item_entity(&mut commands, &assets, &state, id);
     }
+    commands.insert_resource(assets);
 }
 
-pub fn item_position_update(
-    mut query: Query<
-        (&mut Transform, &mut Visibility, &BoardPosition),
-        (Changed<BoardPosition>, With<Item>),
-    >,
-    board: Res<IcoBoard>,
+/// Gives an entity to the items added past the last one, the slots of removed items keep theirs
+pub fn item_added_spawn(
+    mut commands: Commands,
+    state: Res<SimulationState>,
+    assets: Option<Res<ItemAssets>>,
+    query: Query<&Item>,
 ) {
-    for (mut transform, mut visibility, pos) in &mut query {
-        transform.translation = board.world_position(pos).into();
-        visibility.is_visible = true;
+    let assets = match assets {
+        Some(assets) if state.is_changed() => assets,
+        _ => return,
+    };
+    for id in query.iter().count()..state.items.len() {
+        spawn_item_entity(&mut commands, &assets, &state, id);
     }
 }
 
-pub fn item_pickup_update(
-    removals: RemovedComponents<BoardPosition>,
-    mut query: Query<&mut Visibility>,
+fn spawn_item_entity(
+    commands: &mut Commands,
+    assets: &ItemAssets,
+    state: &SimulationState,
+    id: usize,
+) {
+    let kind = state.items[id].kind;
+    let translation = match state.items[id].position {
+        Some(pos) => item_translation(state, id, &pos),
+        None => Vec3::ZERO,
+    };
+    commands
+        .spawn()
+        .insert_bundle(PbrBundle {
+            mesh: assets.mesh.clone(),
+            material: assets.kind_materials[kind % ITEM_COLORS.len()].clone(),
+            transform: Transform {
+                translation,
+                ..default()
+            },
+            visibility: Visibility {
+                is_visible: state.items[id].position.is_some(),
+            },
+            ..default()
+        })
+        .insert(Item { id, kind });
+}
+
+/// Copies the items of the simulation state into their entities, hiding the carried
+/// and removed ones, and the entities left over when the board is rebuilt with fewer items
+pub fn item_sync(
+    state: Res<SimulationState>,
+    assets: Option<Res<ItemAssets>>,
+    mut query: Query<(
+        &mut Item,
+        &mut Transform,
+        &mut Visibility,
+        &mut Handle<StandardMaterial>,
+    )>,
 ) {
-    for entity in removals.iter() {
-        let mut visibility = query.get_mut(entity).unwrap_or_else(|_| {
-            let error_message = "Could not find BoardEntity with removed BoardPosition";
-            error!("{error_message}");
-            panic!("{error_message}");
-        });
-        visibility.is_visible = false;
+    if !state.is_changed() {
+        return;
+    }
+    for (mut item, mut transform, mut visibility, mut material) in &mut query {
+        let item_state = match state.items.get(item.id) {
+            Some(item_state) => item_state,
+            None => {
+                if visibility.is_visible {
+                    visibility.is_visible = false;
+                }
+                continue;
+            }
+        };
+        // Items added in the slot of a removed one may be of another kind
+        if item.kind != item_state.kind {
+            item.kind = item_state.kind;
+            if let Some(assets) = &assets {
+                *material = assets.kind_materials[item.kind % ITEM_COLORS.len()].clone();
+            }
+        }
+        match item_state.position {
+            Some(pos) => {
+                let translation = item_translation(&state, item.id, &pos);
+                if transform.translation != translation {
+                    transform.translation = translation;
+                }
+                if !visibility.is_visible {
+                    visibility.is_visible = true;
+                }
+            }
+            None => {
+                if visibility.is_visible {
+                    visibility.is_visible = false;
+                }
+            }
+        }
     }
 }
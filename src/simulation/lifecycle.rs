@@ -0,0 +1,106 @@
+//! Energy model: ants spend energy moving and carrying, die when exhausted
+//! and new ones are born to keep the colonies going
+
+use bevy::prelude::Vec3;
+use rand::{Rng, RngCore};
+use std::collections::VecDeque;
+
+use crate::simulation::board::{Board, BoardPosition};
+use crate::simulation::foraging::Foraging;
+
+/// Steps between two samples of the population history
+pub const POPULATION_SAMPLE_INTERVAL: u64 = 100;
+/// Samples kept in the population history, the oldest ones being dropped
+pub const POPULATION_HISTORY: usize = 1000;
+
+/// Where the newborn ants appear
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum SpawnLocation {
+    /// Random open cell
+    Random,
+    /// One of the nests when foraging, a random cell otherwise
+    Nests,
+    /// Open cell closest to one of these points, picked at random
+    Points(&'static [[f32; 3]]),
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct LifecycleConfig {
+    /// Energy of every ant when it is born
+    pub energy: f32,
+    /// Spent for every cell moved
+    pub move_cost: f32,
+    /// Spent every step for every carried item
+    pub carry_cost: f32,
+    /// Ants born in each colony every step, on average
+    pub birth_rate: f64,
+    pub spawn_location: SpawnLocation,
+}
+
+impl LifecycleConfig {
+    /// Cell where a newborn ant appears
+    pub fn birth_position(
+        &self,
+        board: &dyn Board,
+        foraging: Option<&Foraging>,
+        rng: &mut dyn RngCore,
+    ) -> BoardPosition {
+        match (self.spawn_location, foraging) {
+            (SpawnLocation::Nests, Some(foraging)) => {
+                foraging.nests[rng.gen_range(0..foraging.nests.len())]
+            }
+            (SpawnLocation::Points(points), _) if !points.is_empty() => {
+                let graph = board.graph();
+                let point = Vec3::from(points[rng.gen_range(0..points.len())]);
+                let closest = graph.closest_cell(point, BoardPosition::default());
+                graph
+                    .find_nearby(closest, |pos| !graph.is_blocked(pos))
+                    .unwrap()
+            }
+            (_, _) => board.new_random_position(rng),
+        }
+    }
+
+    /// Number of ants born in a colony this step, `birth_rate` on average
+    pub fn births(&self, rng: &mut dyn RngCore) -> usize {
+        let whole = self.birth_rate.floor();
+        whole as usize + rng.gen_bool(self.birth_rate - whole) as usize
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Lifecycle {
+    pub config: LifecycleConfig,
+    /// Ants born since the start, not counting the first ones
+    pub births: usize,
+    /// Ants dead of exhaustion since the start
+    pub deaths: usize,
+    /// Living ants every [`POPULATION_SAMPLE_INTERVAL`] steps, the last [`POPULATION_HISTORY`] of them
+    pub population: VecDeque<usize>,
+    /// Step of the next population sample
+    next_sample: u64,
+}
+
+impl Lifecycle {
+    pub fn new(config: LifecycleConfig) -> Self {
+        Self {
+            config,
+            births: 0,
+            deaths: 0,
+            population: VecDeque::with_capacity(POPULATION_HISTORY),
+            next_sample: POPULATION_SAMPLE_INTERVAL,
+        }
+    }
+
+    /// Samples the population after `step` steps, once every [`POPULATION_SAMPLE_INTERVAL`]
+    pub fn record(&mut self, step: u64, population: usize) {
+        if step < self.next_sample {
+            return;
+        }
+        self.next_sample = step + POPULATION_SAMPLE_INTERVAL;
+        if self.population.len() == POPULATION_HISTORY {
+            self.population.pop_front();
+        }
+        self.population.push_back(population);
+    }
+}
@@ -1,4 +1,32 @@
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::simulation::control::SimulationStatus;
+use crate::simulation::state::SimulationState;
+
+pub mod aco;
 pub mod ant;
 pub mod board;
+pub mod colony;
 pub mod control;
+pub mod crowding;
+pub mod disturbance;
+pub mod foraging;
 pub mod item;
+pub mod lifecycle;
+pub mod state;
+
+/// Stage with the systems run once per fixed step
+pub fn simulation_stage() -> SystemStage {
+    SystemStage::parallel().with_system_set(
+        ConditionSet::new()
+            .run_if_not(control::is_simulation_paused)
+            .with_system(simulation_step)
+            .with_system(aco::aco_step)
+            .into(),
+    )
+}
+
+pub fn simulation_step(status: Res<SimulationStatus>, mut state: ResMut<SimulationState>) {
+    state.step(status.ending);
+}
@@ -0,0 +1,1009 @@
+//! Simulation core, independent of the ECS.
+//! Rendering reads it through the sync systems of [`ant`](crate::simulation::ant)
+//! and [`item`](crate::simulation::item), headless runs drive it directly
+
+use bevy::log::error;
+use bevy::math::Vec3;
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::ops::Range;
+
+use crate::consts::{
+    ANT_MEMORY, COLONIES, CROWDING, DISTURBANCE, ITEM_KINDS, LIFECYCLE, PHEROMONE,
+};
+use crate::simulation::ant::{
+    step_towards, AntParameters, Memory, MemoryConfig, MovementModel, ParameterSummary,
+};
+use crate::simulation::board::{Board, BoardPosition, PheromoneField};
+use crate::simulation::colony::{Colony, DropPolicy};
+use crate::simulation::crowding::{
+    BlockedMove, Crowding, Exchange, ExclusionConfig, InteractionConfig,
+};
+use crate::simulation::disturbance::Disturbance;
+use crate::simulation::foraging::{Foraging, ForagingConfig};
+use crate::simulation::lifecycle::{Lifecycle, LifecycleConfig};
+
+/// Minimum number of ants processed by each parallel task
+const ANT_BATCH_SIZE: usize = 1024;
+
+/// What the ant decided to do this step, applied during the commit phase
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq)]
+pub enum AntAction {
+    #[default]
+    Idle,
+    Pickup,
+    Drop,
+}
+
+/// How ants and items are placed on a rebuilt board
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Respawn {
+    /// Random cells, as in a new run
+    Fresh,
+    /// Closest free cell to where they were, blocked cells are carried over too
+    Closest,
+}
+
+#[derive(Debug, Clone)]
+pub struct AntState {
+    /// Index of its colony in [`SimulationState::colonies`]
+    pub colony: usize,
+    /// Dead ants keep their slot, which the next ant born takes over
+    pub alive: bool,
+    /// Only spent with the energy model, the ant dies when it runs out
+    pub energy: f32,
+    pub position: BoardPosition,
+    /// Indices of the carried items in [`SimulationState::items`], the last one picked up at the end
+    pub items: Vec<usize>,
+    /// Dropping its items one per step, standing still until empty
+    pub unloading: bool,
+    pub action: AntAction,
+    /// Tangent direction of the last move, used by some [`MovementModel`]s
+    pub heading: Vec3,
+    /// Drawn when it spawns. The ant only decides once it has made all the moves of its speed
+    pub parameters: AntParameters,
+    /// Not acting at all this step, out of laziness
+    pub resting: bool,
+    /// Cells visited during the last step, starting with the one it started from,
+    /// used to draw the ant walking between them
+    pub path: Vec<BoardPosition>,
+    pub memory: Memory,
+    /// Random stream owned by a single ant, so results don't depend on thread scheduling
+    rng: SmallRng,
+}
+
+#[derive(Debug, Clone)]
+pub struct ItemState {
+    /// `None` while carried by an ant or once removed
+    pub position: Option<BoardPosition>,
+    /// Only the colonies handling this kind see and pick it up
+    pub kind: usize,
+    /// Delivered, decayed or removed in a zone, the next item added takes over the slot
+    pub removed: bool,
+    /// Step when the item was last placed on the board, to make it decay
+    pub placed: u64,
+}
+
+pub struct SimulationState {
+    pub board: Box<dyn Board>,
+    pub ants: Vec<AntState>,
+    pub items: Vec<ItemState>,
+    pub step_count: u64,
+    pub colonies: Vec<Colony>,
+    pub memory: MemoryConfig,
+    pub pheromone: PheromoneField,
+    /// Set in the nest and forage mode, where ants bring items home instead of sorting them
+    pub foraging: Option<Foraging>,
+    /// Set with the energy model, where ants die and new ones are born
+    pub lifecycle: Option<Lifecycle>,
+    /// Set when items keep being added and removed during the run
+    pub disturbance: Option<Disturbance>,
+    /// Set when ants crowd each other out of the cells or interact when they meet
+    pub crowding: Option<Crowding>,
+    /// Used for setup and to seed the stream of every ant,
+    /// so a run is reproducible for a given seed
+    rng: SmallRng,
+}
+
+impl SimulationState {
+    pub fn new(mut board: Box<dyn Board>, seed: u64) -> Self {
+        let colonies: Vec<_> = COLONIES.iter().copied().map(Colony::new).collect();
+        add_visions(board.as_mut(), &colonies);
+        Self {
+            pheromone: PheromoneField::new(PHEROMONE, board.size()),
+            board,
+            ants: Vec::new(),
+            items: Vec::new(),
+            step_count: 0,
+            colonies,
+            memory: ANT_MEMORY,
+            foraging: None,
+            lifecycle: LIFECYCLE.map(Lifecycle::new),
+            disturbance: DISTURBANCE.map(Disturbance::new),
+            crowding: CROWDING.map(Crowding::new),
+            rng: SmallRng::seed_from_u64(seed),
+        }
+    }
+
+    /// Switches to the nest and forage mode, the trail replacing the pheromone.
+    /// Call before spawning ants and items
+    pub fn start_foraging(&mut self, config: ForagingConfig) {
+        self.foraging = Some(Foraging::new(config, self.board.as_ref()));
+        self.pheromone.config = config.trail;
+    }
+
+    /// Gives every colony the same movement model
+    pub fn set_movement(&mut self, movement: MovementModel) {
+        for colony in &mut self.colonies {
+            colony.config.movement = movement;
+        }
+    }
+
+    /// Gives every colony the same drop policy
+    pub fn set_drop_policy(&mut self, drop_policy: DropPolicy) {
+        for colony in &mut self.colonies {
+            colony.config.drop_policy = drop_policy;
+        }
+    }
+
+    /// Spawns the configured number of ants of every colony, returning their indices
+    pub fn spawn_colonies(&mut self) -> Range<usize> {
+        let start = self.ants.len();
+        for colony in 0..self.colonies.len() {
+            self.spawn_ants(colony, self.colonies[colony].config.count);
+        }
+        start..self.ants.len()
+    }
+
+    /// Places `count` ants of `colony` on random cells, or spread over the nests when foraging,
+    /// returning their indices. The visions their radii need are added if the colony changed
+    pub fn spawn_ants(&mut self, colony: usize, count: usize) -> Range<usize> {
+        add_visions(self.board.as_mut(), &self.colonies[colony..=colony]);
+        let start = self.ants.len();
+        for _ in 0..count {
+            let position = match &self.foraging {
+                Some(foraging) => foraging.nests[self.ants.len() % foraging.nests.len()],
+                None => self.board.new_random_position(&mut self.rng),
+            };
+            let ant = self.new_ant(colony, position);
+            self.ants.push(ant);
+        }
+        start..self.ants.len()
+    }
+
+    fn new_ant(&mut self, colony: usize, position: BoardPosition) -> AntState {
+        let config = &self.colonies[colony].config;
+        let parameters = config
+            .parameters
+            .sample(config.vision_radius, &mut self.rng);
+        AntState {
+            colony,
+            alive: true,
+            energy: self
+                .lifecycle
+                .as_ref()
+                .map_or(0., |lifecycle| lifecycle.config.energy),
+            position,
+            items: Vec::new(),
+            unloading: false,
+            action: AntAction::Idle,
+            heading: Vec3::ZERO,
+            parameters,
+            resting: false,
+            path: Vec::new(),
+            memory: Memory::default(),
+            rng: SmallRng::seed_from_u64(self.rng.gen()),
+        }
+    }
+
+    /// Adds an ant of `colony` where the energy model places newborns,
+    /// in the slot of a dead ant when there is one
+    fn give_birth(&mut self, colony: usize, config: &LifecycleConfig) {
+        let position =
+            config.birth_position(self.board.as_ref(), self.foraging.as_ref(), &mut self.rng);
+        let ant = self.new_ant(colony, position);
+        match self.ants.iter().position(|ant| !ant.alive) {
+            Some(id) => self.ants[id] = ant,
+            None => self.ants.push(ant),
+        }
+    }
+
+    /// Places `count` items on random cells which aren't full, returning their indices
+    pub fn spawn_items(&mut self, count: usize) -> Range<usize> {
+        let free_slots = self.board.graph().free_slots();
+        if count > free_slots {
+            let error_message = format!(
+                "More food ({}) than room on the board ({})",
+                count, free_slots
+            );
+            error!("{error_message}");
+            panic!("{error_message}");
+        }
+
+        let start = self.items.len();
+        for _ in 0..count {
+            let position = loop {
+                let pos = self.board.new_random_position(&mut self.rng);
+                if !self.board.is_full(&pos) {
+                    break pos;
+                }
+            };
+            let kind = default_kind(self.items.len());
+            self.board.place_item(&position, self.items.len(), kind);
+            self.items.push(ItemState {
+                position: Some(position),
+                kind,
+                removed: false,
+                placed: self.step_count,
+            });
+        }
+        start..self.items.len()
+    }
+
+    /// Piles the food of the sources on the cells closest to their center which aren't full,
+    /// returning the indices of the items
+    pub fn spawn_food_sources(&mut self) -> Range<usize> {
+        let foraging = self
+            .foraging
+            .as_ref()
+            .expect("Food sources are only spawned when foraging");
+        let start = self.items.len();
+        for source in foraging.config.sources {
+            let graph = self.board.graph();
+            let center = graph.closest_cell(source.center.into(), BoardPosition::default());
+            for _ in 0..source.quantity {
+                let graph = self.board.graph();
+                let free = graph.find_nearby(center, |pos| {
+                    !graph.is_blocked(pos) && !graph.is_full(pos) && !foraging.is_nest(pos)
+                });
+                let position = match free {
+                    Some(position) => position,
+                    None => {
+                        let error_message = format!(
+                            "More food ({}) than room on the board ({})",
+                            source.quantity,
+                            self.board.graph().free_slots()
+                        );
+                        error!("{error_message}");
+                        panic!("{error_message}");
+                    }
+                };
+                self.board.place_item(&position, self.items.len(), 0);
+                self.items.push(ItemState {
+                    position: Some(position),
+                    kind: 0,
+                    removed: false,
+                    placed: self.step_count,
+                });
+            }
+        }
+        start..self.items.len()
+    }
+
+    /// Places a new item at `position`, in the slot of a removed item when there is one,
+    /// returning its index. Its kind cycles through the kinds with the index when `None`
+    fn add_item(&mut self, position: BoardPosition, kind: Option<usize>) -> usize {
+        let id = self
+            .items
+            .iter()
+            .position(|item| item.removed)
+            .unwrap_or(self.items.len());
+        let kind = kind.unwrap_or_else(|| default_kind(id));
+        let item = ItemState {
+            position: Some(position),
+            kind,
+            removed: false,
+            placed: self.step_count,
+        };
+        match self.items.get_mut(id) {
+            Some(slot) => *slot = item,
+            None => self.items.push(item),
+        }
+        self.board.place_item(&position, id, kind);
+        id
+    }
+
+    /// Takes the item `id` off the board for good
+    fn remove_item(&mut self, id: usize) {
+        let item = &mut self.items[id];
+        if let Some(pos) = item.position.take() {
+            self.board.remove_item(&pos, id);
+        }
+        item.removed = true;
+    }
+
+    /// Replaces the board, keeping the number of ants and items, removed items left out.
+    /// Fails, leaving the state untouched, when the items don't fit on the new board
+    pub fn rebuild(&mut self, mut board: Box<dyn Board>, respawn: Respawn) -> Result<(), String> {
+        add_visions(board.as_mut(), &self.colonies);
+        match respawn {
+            Respawn::Fresh => {
+                let item_count = self.items.iter().filter(|item| !item.removed).count();
+                if item_count > board.graph().free_slots() {
+                    return Err(format!(
+                        "More food ({}) than room on the board ({})",
+                        item_count,
+                        board.graph().free_slots()
+                    ));
+                }
+                let mut ant_counts = vec![0; self.colonies.len()];
+                for ant in self.ants.iter().filter(|ant| ant.alive) {
+                    ant_counts[ant.colony] += 1;
+                }
+                self.board = board;
+                self.ants.clear();
+                self.items.clear();
+                self.step_count = 0;
+                if let Some(foraging) = &mut self.foraging {
+                    *foraging = Foraging::new(foraging.config, self.board.as_ref());
+                }
+                if let Some(lifecycle) = &mut self.lifecycle {
+                    *lifecycle = Lifecycle::new(lifecycle.config);
+                }
+                if let Some(disturbance) = &mut self.disturbance {
+                    *disturbance = Disturbance::new(disturbance.config);
+                }
+                if let Some(crowding) = &mut self.crowding {
+                    *crowding = Crowding::new(crowding.config);
+                }
+                for colony in &mut self.colonies {
+                    *colony = Colony::new(colony.config);
+                }
+                for (colony, count) in ant_counts.into_iter().enumerate() {
+                    self.spawn_ants(colony, count);
+                }
+                if self.foraging.is_some() {
+                    self.spawn_food_sources();
+                } else {
+                    self.spawn_items(item_count);
+                }
+            }
+            Respawn::Closest => {
+                let old = self.board.graph();
+                let mut closest = BoardPosition::default();
+                for i in 0..board.size() {
+                    let pos = BoardPosition::new(i);
+                    closest = old.closest_cell(board.world_position(&pos).into(), closest);
+                    if old.is_blocked(&closest) && !board.is_blocked(&pos) {
+                        board.set_blocked(&pos, true);
+                    }
+                }
+                if self.items_on_board() > board.graph().free_slots() {
+                    return Err(format!(
+                        "More food ({}) than room on the board ({})",
+                        self.items_on_board(),
+                        board.graph().free_slots()
+                    ));
+                }
+
+                let new = board.graph();
+                let mut closest = BoardPosition::default();
+                let ant_positions: Vec<_> = self
+                    .ants
+                    .iter()
+                    .map(|ant| {
+                        closest =
+                            new.closest_cell(old.world_position(&ant.position).into(), closest);
+                        new.find_nearby(closest, |pos| !new.is_blocked(pos))
+                            .unwrap()
+                    })
+                    .collect();
+
+                let mut item_positions = Vec::with_capacity(self.items.len());
+                for (id, item) in self.items.iter().enumerate() {
+                    let position = item.position.map(|pos| {
+                        closest = board
+                            .graph()
+                            .closest_cell(old.world_position(&pos).into(), closest);
+                        let free = board
+                            .graph()
+                            .find_nearby(closest, |pos| {
+                                !board.is_blocked(pos) && !board.is_full(pos)
+                            })
+                            .unwrap();
+                        board.place_item(&free, id, item.kind);
+                        free
+                    });
+                    item_positions.push(position);
+                }
+
+                for (ant, position) in self.ants.iter_mut().zip(ant_positions) {
+                    ant.position = position;
+                    ant.path.clear();
+                    ant.memory.forget();
+                }
+                for (item, position) in self.items.iter_mut().zip(item_positions) {
+                    item.position = position;
+                }
+                self.board = board;
+                if let Some(foraging) = &mut self.foraging {
+                    foraging.nests = Foraging::new(foraging.config, self.board.as_ref()).nests;
+                }
+            }
+        }
+        self.pheromone.reset(self.board.size());
+        Ok(())
+    }
+
+    pub fn items_on_board(&self) -> usize {
+        self.items
+            .iter()
+            .filter(|item| item.position.is_some())
+            .count()
+    }
+
+    /// Runs one fixed step.
+    /// Ants move and decide in parallel reading the board, then the decisions are
+    /// applied in ant order so when two ants compete for the same cell the same one
+    /// wins on every run. With the exclusion rule, the moves are settled in ant order
+    /// before the ants decide
+    pub fn step(&mut self, ending: bool) {
+        let pheromone = &self.pheromone;
+        let attraction = |pos: &BoardPosition| pheromone.attraction(pos);
+        let context = StepContext {
+            board: self.board.as_ref(),
+            colonies: &self.colonies,
+            items: &self.items,
+            memory: &self.memory,
+            pheromone,
+            foraging: self.foraging.as_ref(),
+            lifecycle: self.lifecycle.as_ref().map(|lifecycle| &lifecycle.config),
+            bias: if pheromone.config.is_enabled() && pheromone.config.attraction != 0. {
+                Some(&attraction)
+            } else {
+                None
+            },
+        };
+        let walk = |ant: &mut AntState| {
+            ant.path.clear();
+            let laziness = ant.parameters.laziness;
+            ant.resting = ant.alive && laziness > 0. && ant.rng.gen_bool(laziness);
+            if ant.alive && !ant.resting && !ant.unloading && (!ending || !ant.items.is_empty()) {
+                ant.walk(&context);
+            }
+        };
+        let decide = |ant: &mut AntState| {
+            if !ant.alive {
+                ant.action = AntAction::Idle;
+                return;
+            }
+            if let Some(lifecycle) = context.lifecycle {
+                let moves = ant.path.len().saturating_sub(1);
+                ant.energy -= lifecycle.move_cost * moves as f32
+                    + lifecycle.carry_cost * ant.items.len() as f32;
+            }
+            ant.action = if ant.resting {
+                AntAction::Idle
+            } else {
+                ant.decide(&context, ending)
+            };
+        };
+        let exclusion = self
+            .crowding
+            .as_ref()
+            .and_then(|crowding| crowding.config.exclusion);
+        match exclusion {
+            Some(exclusion) => {
+                self.ants
+                    .par_iter_mut()
+                    .with_min_len(ANT_BATCH_SIZE)
+                    .for_each(walk);
+                let blocked = exclude(context.board, context.foraging, &mut self.ants, &exclusion);
+                self.crowding.as_mut().unwrap().blocked += blocked;
+                self.ants
+                    .par_iter_mut()
+                    .with_min_len(ANT_BATCH_SIZE)
+                    .for_each(decide);
+            }
+            _ => self
+                .ants
+                .par_iter_mut()
+                .with_min_len(ANT_BATCH_SIZE)
+                .for_each(|ant| {
+                    walk(ant);
+                    decide(ant);
+                }),
+        }
+
+        if let Some(foraging) = &mut self.foraging {
+            foraging.delivered_last_step = 0;
+        }
+        for ant in &mut self.ants {
+            if self.lifecycle.is_some() && ant.alive && ant.energy <= 0. {
+                // Exhausted ants leave what they carry on the closest cells with room,
+                // the items are lost once the board is full
+                for item in ant.items.drain(..) {
+                    let graph = self.board.graph();
+                    let foraging = self.foraging.as_ref();
+                    let free = graph.find_nearby(ant.position, |pos| {
+                        !graph.is_blocked(pos)
+                            && !graph.is_full(pos)
+                            && !matches!(foraging, Some(foraging) if foraging.is_nest(pos))
+                    });
+                    match free {
+                        Some(free) => {
+                            self.board.place_item(&free, item, self.items[item].kind);
+                            self.items[item].position = Some(free);
+                            self.items[item].placed = self.step_count;
+                        }
+                        None => self.items[item].removed = true,
+                    }
+                }
+                ant.alive = false;
+                ant.unloading = false;
+                ant.path.clear();
+                ant.memory.forget();
+                if let Some(lifecycle) = &mut self.lifecycle {
+                    lifecycle.deaths += 1;
+                }
+                continue;
+            }
+            match ant.action {
+                // Another ant may have stacked an item of another kind on top meanwhile
+                AntAction::Pickup
+                    if self.colonies[ant.colony].config.handles_top(
+                        self.board.as_ref(),
+                        &self.items,
+                        &ant.position,
+                    ) =>
+                {
+                    if let Some(item) = self.board.take_item(&ant.position) {
+                        self.items[item].position = None;
+                        ant.items.push(item);
+                        self.colonies[ant.colony].pickups += 1;
+                    }
+                }
+                AntAction::Drop if self.foraging.is_some() => {
+                    // Delivered food leaves the board for good
+                    let foraging = self.foraging.as_mut().unwrap();
+                    foraging.delivered += 1;
+                    foraging.delivered_last_step += 1;
+                    let item = ant.items.pop().unwrap();
+                    self.items[item].removed = true;
+                    self.colonies[ant.colony].drops += 1;
+                }
+                AntAction::Drop => {
+                    let target = self.colonies[ant.colony].config.drop_target(
+                        self.board.as_ref(),
+                        &ant.position,
+                        ant.parameters.vision_radius,
+                        ant.unloading,
+                        &mut ant.rng,
+                    );
+                    match target {
+                        Some(target) => {
+                            let item = ant.items.pop().unwrap();
+                            self.board.place_item(&target, item, self.items[item].kind);
+                            self.items[item].position = Some(target);
+                            self.items[item].placed = self.step_count;
+                            self.colonies[ant.colony].drops += 1;
+                            self.pheromone
+                                .deposit(&target, self.pheromone.config.drop_deposit);
+                            ant.unloading = !ant.items.is_empty();
+                        }
+                        // Keeps carrying what is left
+                        None => ant.unloading = false,
+                    }
+                }
+                AntAction::Pickup | AntAction::Idle => {}
+            }
+        }
+
+        if let Some(interaction) = self
+            .crowding
+            .as_ref()
+            .and_then(|crowding| crowding.config.interaction)
+        {
+            let exchanges = self.interact(&interaction);
+            self.crowding.as_mut().unwrap().exchanges += exchanges;
+        }
+
+        if self.pheromone.config.is_enabled() {
+            let carry_deposit = self.pheromone.config.carry_deposit;
+            for ant in self.ants.iter().filter(|ant| !ant.items.is_empty()) {
+                self.pheromone.deposit(&ant.position, carry_deposit);
+            }
+            self.pheromone.update(self.board.as_ref());
+        }
+
+        if let Some(config) = self.lifecycle.as_ref().map(|lifecycle| lifecycle.config) {
+            let mut births = 0;
+            for colony in 0..self.colonies.len() {
+                for _ in 0..config.births(&mut self.rng) {
+                    self.give_birth(colony, &config);
+                    births += 1;
+                }
+            }
+            let (step, population) = (self.step_count + 1, self.population());
+            let lifecycle = self.lifecycle.as_mut().unwrap();
+            lifecycle.births += births;
+            lifecycle.record(step, population);
+        }
+
+        if self.disturbance.is_some() {
+            self.disturb();
+        }
+
+        self.step_count += 1;
+    }
+
+    /// Pairs up the living ants of the same colony ending the step on the same cell, in ant order,
+    /// returning how many pairs exchanged something
+    fn interact(&mut self, config: &InteractionConfig) -> usize {
+        let mut waiting = HashMap::new();
+        let mut exchanges = 0;
+        for id in 0..self.ants.len() {
+            let ant = &self.ants[id];
+            if !ant.alive || ant.unloading {
+                continue;
+            }
+            let key = (ant.position.idx(), ant.colony);
+            match waiting.remove(&key) {
+                Some(other) => {
+                    if self.rng.gen_bool(config.probability) && self.exchange(other, id, config) {
+                        exchanges += 1;
+                    }
+                }
+                None => {
+                    waiting.insert(key, id);
+                }
+            }
+        }
+        exchanges
+    }
+
+    /// Lets the ants `first` and `second`, with `first < second`, exchange items or memory,
+    /// returning whether anything changed hands
+    fn exchange(&mut self, first: usize, second: usize, config: &InteractionConfig) -> bool {
+        let (head, tail) = self.ants.split_at_mut(second);
+        let (first, second) = (&mut head[first], &mut tail[0]);
+        match config.exchange {
+            Exchange::Items => {
+                let (giver, taker) = if first.items.len() > second.items.len() {
+                    (first, second)
+                } else {
+                    (second, first)
+                };
+                // Both ants belong to the same colony, so the taker has room and handles the item
+                if giver.items.len() == taker.items.len() {
+                    return false;
+                }
+                taker.items.push(giver.items.pop().unwrap());
+                true
+            }
+            Exchange::Memory => {
+                let size = self.memory.size;
+                let memory = first.memory.clone();
+                first.memory.learn(&second.memory, size);
+                second.memory.learn(&memory, size);
+                size > 0
+            }
+        }
+    }
+
+    /// Removes the items which decayed or lie in a removal zone, then adds the items of the sources
+    fn disturb(&mut self) {
+        let config = self.disturbance.as_ref().unwrap().config;
+        let (mut decayed, mut removed, mut added) = (0, 0, 0);
+        for id in 0..self.items.len() {
+            let (pos, age) = match &self.items[id] {
+                ItemState {
+                    position: Some(pos),
+                    placed,
+                    ..
+                } => (*pos, self.step_count - placed),
+                _ => continue,
+            };
+            if matches!(config.decay, Some(decay) if age >= decay) {
+                self.remove_item(id);
+                decayed += 1;
+            } else if config.zones.iter().any(|zone| {
+                zone.contains(self.board.as_ref(), &pos) && self.rng.gen_bool(zone.rate)
+            }) {
+                self.remove_item(id);
+                removed += 1;
+            }
+        }
+
+        for source in config.sources {
+            for _ in 0..source.count(&mut self.rng) {
+                let foraging = self.foraging.as_ref();
+                let allowed = |pos: &BoardPosition| !matches!(foraging, Some(foraging) if foraging.is_nest(pos));
+                match source.position(self.board.as_ref(), &allowed, &mut self.rng) {
+                    Some(position) => {
+                        self.add_item(position, source.kind);
+                        added += 1;
+                    }
+                    // The board is full
+                    None => break,
+                }
+            }
+        }
+
+        let disturbance = self.disturbance.as_mut().unwrap();
+        disturbance.decayed += decayed;
+        disturbance.removed += removed;
+        disturbance.added += added;
+    }
+
+    /// Number of living ants
+    pub fn population(&self) -> usize {
+        self.ants.iter().filter(|ant| ant.alive).count()
+    }
+
+    /// Mean ratio of occupied cells within the vision of the items lying on the board,
+    /// higher values mean tighter clusters
+    pub fn mean_item_density(&self) -> f64 {
+        let (sum, count) = self
+            .items
+            .iter()
+            .filter_map(|item| item.position)
+            .fold((0., 0), |(sum, count), pos| {
+                (sum + self.board.item_density(&pos), count + 1)
+            });
+        if count == 0 {
+            0.
+        } else {
+            sum / count as f64
+        }
+    }
+
+    /// Mean ratio of cells holding an item handled by `colony`, as seen by its ants,
+    /// around the items of those kinds lying on the board
+    pub fn colony_item_density(&self, colony: usize) -> f64 {
+        let config = &self.colonies[colony].config;
+        let (sum, count) = self
+            .items
+            .iter()
+            .filter(|item| config.handles(item.kind))
+            .filter_map(|item| item.position)
+            .fold((0., 0), |(sum, count), pos| {
+                (
+                    sum + config.item_density(self.board.as_ref(), &pos),
+                    count + 1,
+                )
+            });
+        if count == 0 {
+            0.
+        } else {
+            sum / count as f64
+        }
+    }
+
+    /// Spread of every parameter over the living ants, in the order of [`AntParameters::NAMES`]
+    pub fn parameter_summaries(&self) -> [ParameterSummary; 4] {
+        let alive: Vec<_> = self.ants.iter().filter(|ant| ant.alive).collect();
+        [0, 1, 2, 3]
+            .map(|i| ParameterSummary::new(alive.iter().map(|ant| ant.parameters.values()[i])))
+    }
+
+    /// Whether the ants of some colony get different parameters
+    pub fn has_varying_parameters(&self) -> bool {
+        self.colonies
+            .iter()
+            .any(|colony| !colony.config.parameters.is_constant())
+    }
+
+    /// Number of ants of `colony` and how many of them carry an item
+    pub fn colony_ant_counts(&self, colony: usize) -> (usize, usize) {
+        self.ants
+            .iter()
+            .filter(|ant| ant.alive && ant.colony == colony)
+            .fold((0, 0), |(ants, carrying), ant| {
+                (ants + 1, carrying + !ant.items.is_empty() as usize)
+            })
+    }
+}
+
+/// Everything the ants read while moving and deciding in parallel
+struct StepContext<'a> {
+    board: &'a dyn Board,
+    colonies: &'a [Colony],
+    items: &'a [ItemState],
+    memory: &'a MemoryConfig,
+    pheromone: &'a PheromoneField,
+    foraging: Option<&'a Foraging>,
+    lifecycle: Option<&'a LifecycleConfig>,
+    bias: Option<&'a (dyn Fn(&BoardPosition) -> f64 + Sync)>,
+}
+
+impl AntState {
+    /// Carrying ants head for the closest nest when foraging, once full or when they don't see
+    /// any more food, and otherwise for the densest cell they remember, if any
+    fn walk(&mut self, context: &StepContext) {
+        let StepContext {
+            board,
+            memory,
+            foraging,
+            bias,
+            ..
+        } = *context;
+        let colony = &context.colonies[self.colony].config;
+        self.path.push(self.position);
+        let full = self.items.len() >= colony.capacity;
+        let target = match (self.items.is_empty(), foraging) {
+            (true, _) => None,
+            (false, Some(foraging)) if full || board.items_in_vision(&self.position) == 0 => {
+                Some((
+                    foraging.closest_nest(board, &self.position),
+                    foraging.config.homing,
+                ))
+            }
+            (false, Some(_)) => None,
+            (false, None) => self.memory.densest().map(|target| (target, memory.bias)),
+        };
+        for _ in 0..self.parameters.speed {
+            match target {
+                // Food is delivered once the move is over, so the ant waits on its nest
+                Some((target, _)) if target == self.position && foraging.is_some() => break,
+                Some((target, bias)) if target != self.position => step_towards(
+                    board,
+                    &mut self.position,
+                    &target,
+                    bias,
+                    &mut self.heading,
+                    &mut self.path,
+                    &mut self.rng,
+                ),
+                _ => colony.movement.walk(
+                    board,
+                    &mut self.position,
+                    &mut self.heading,
+                    &mut self.path,
+                    bias.map(|bias| bias as &dyn Fn(&BoardPosition) -> f64),
+                    &mut self.rng,
+                ),
+            }
+        }
+    }
+
+    /// Ants only see and pick up the items of the kinds their colony handles,
+    /// and keep picking them up until they carry as many as their colony allows
+    fn decide(&mut self, context: &StepContext, ending: bool) -> AntAction {
+        let StepContext {
+            board,
+            items,
+            memory,
+            pheromone,
+            foraging,
+            ..
+        } = *context;
+        let colony = &context.colonies[self.colony].config;
+        let handled = colony.handles_top(board, items, &self.position);
+        let (empty, full) = (self.items.is_empty(), self.items.len() >= colony.capacity);
+        if self.unloading {
+            return AntAction::Drop;
+        }
+
+        // Foragers take any food they find and leave it at the nest
+        if let Some(foraging) = foraging {
+            return match (handled, empty) {
+                (true, _) if !full && !ending => AntAction::Pickup,
+                (_, false) if foraging.is_nest(&self.position) => AntAction::Drop,
+                (_, _) => AntAction::Idle,
+            };
+        }
+
+        let density =
+            colony.item_density_within(board, &self.position, self.parameters.vision_radius);
+        // The steepness scales the density before it turns into probabilities
+        let felt = (density * self.parameters.steepness).min(1.);
+        let drop_prob = colony.probability.drop(felt);
+        let drop_prob = if pheromone.config.is_enabled() {
+            pheromone.boost_drop(&self.position, drop_prob)
+        } else {
+            drop_prob
+        };
+
+        if memory.size > 0 && handled {
+            self.memory.remember(self.position, density, memory.size);
+        }
+        match (handled, empty) {
+            (true, _) if !full && !ending && self.rng.gen_bool(colony.probability.pickup(felt)) => {
+                AntAction::Pickup
+            }
+            (_, false)
+                if colony.can_drop(board, &self.position) && self.rng.gen_bool(drop_prob) =>
+            {
+                AntAction::Drop
+            }
+            (_, _) => AntAction::Idle,
+        }
+    }
+}
+
+/// Kind of the item `id` when none is given, cycling through the kinds
+#[allow(clippy::modulo_one)]
+fn default_kind(id: usize) -> usize {
+    id % ITEM_KINDS
+}
+
+/// Precomputes what the cells see up to every vision radius a colony or its ants may have,
+/// so that ants seeing another radius than the board don't search the cells around
+fn add_visions(board: &mut dyn Board, colonies: &[Colony]) {
+    for colony in colonies {
+        let config = &colony.config;
+        board.graph_mut().add_vision(config.vision_radius);
+        for radius in config.parameters.vision_radii(config.vision_radius) {
+            board.graph_mut().add_vision(radius);
+        }
+    }
+}
+
+/// Sends the ants which walked to a crowded cell back, in ant order, to where they started
+/// or to a neighbour with room, returning how many were blocked
+fn exclude(
+    board: &dyn Board,
+    foraging: Option<&Foraging>,
+    ants: &mut [AntState],
+    config: &ExclusionConfig,
+) -> usize {
+    let start_of = |ant: &AntState| ant.path.first().copied().unwrap_or(ant.position);
+    // Ants yet to be settled still count on the cell they start from
+    let mut counts = vec![0; board.size()];
+    for ant in ants.iter().filter(|ant| ant.alive) {
+        counts[start_of(ant).idx()] += 1;
+    }
+    let has_room = |counts: &[usize], pos: &BoardPosition| {
+        counts[pos.idx()] < config.max_ants
+            || matches!(foraging, Some(foraging) if foraging.is_nest(pos))
+    };
+
+    let mut blocked = 0;
+    for ant in ants.iter_mut().filter(|ant| ant.alive) {
+        let start = start_of(ant);
+        counts[start.idx()] -= 1;
+        if ant.position != start && !has_room(&counts, &ant.position) {
+            blocked += 1;
+            let retried = match config.blocked {
+                BlockedMove::Wait => None,
+                BlockedMove::Retry(tries) => (0..tries)
+                    .map(|_| board.get_random_adjacent(&start, &mut ant.rng))
+                    .find(|pos| *pos != start && has_room(&counts, pos)),
+            };
+            ant.position = retried.unwrap_or(start);
+            ant.path.clear();
+            ant.path.push(start);
+            if ant.position != start {
+                ant.path.push(ant.position);
+            }
+        }
+        counts[ant.position.idx()] += 1;
+    }
+    blocked
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::simulation::board::BoardTopology;
+    use crate::simulation::lifecycle::SpawnLocation;
+
+    #[test]
+    fn exhausted_ant_on_full_board_loses_its_items() {
+        let mut state = SimulationState::new(BoardTopology::Torus.build(1).unwrap(), 1);
+        state.lifecycle = Some(Lifecycle::new(LifecycleConfig {
+            energy: 0.,
+            move_cost: 0.,
+            carry_cost: 0.,
+            birth_rate: 0.,
+            spawn_location: SpawnLocation::Random,
+        }));
+        state.spawn_ants(0, 1);
+        let carried = state.spawn_items(1).start;
+        state
+            .board
+            .take_item(&state.items[carried].position.take().unwrap());
+        state.ants[0].items.push(carried);
+        state.spawn_items(state.board.graph().free_slots());
+
+        state.step(false);
+
+        assert!(!state.ants[0].alive);
+        assert!(state.ants[0].items.is_empty());
+        assert!(state.items[carried].removed);
+        assert_eq!(state.items_on_board(), state.items.len() - 1);
+    }
+}
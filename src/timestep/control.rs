@@ -1,4 +1,6 @@
-use crate::{FixedTimestepConfig, Input, KeyCode, Res, ResMut};
+use bevy::prelude::*;
+
+use crate::timestep::fixed_timestep::FixedTimestepConfig;
 
 pub fn timestep_input_handler(
     kbd: Res<Input<KeyCode>>,
@@ -1,5 +1,5 @@
 use bevy::diagnostic::{Diagnostic, DiagnosticId, Diagnostics};
-use crate::{App, Plugin, ResMut};
+use bevy::prelude::*;
 
 /// Adds "fixed_timestep" diagnostic to an App, specifically "step time", "sps", "step count", "overstep" and "accumulator"
 #[derive(Default)]
@@ -24,6 +24,25 @@ impl FixedTimestepConfig {
     }
 }
 
+/// Written by the [`FixedTimestepStage`] every frame, after running the steps due
+#[derive(Debug, Default, Copy, Clone)]
+pub struct FixedTimestepInfo {
+    pub step: Duration,
+    /// Time accumulated towards the next step
+    pub accumulator: Duration,
+}
+
+impl FixedTimestepInfo {
+    /// How far along the next step the frame is, between 0 and 1
+    pub fn overstep(&self) -> f32 {
+        if self.step > Duration::ZERO {
+            (self.accumulator.as_secs_f32() / self.step.as_secs_f32()).min(1.)
+        } else {
+            1.
+        }
+    }
+}
+
 /// A Stage that runs a number of child stages with a fixed timestep
 ///
 /// You can set the timestep duration. Every frame update, the time delta
@@ -130,5 +149,10 @@ impl Stage for FixedTimestepStage {
                 stage.run(world);
             }
         }
+
+        world.insert_resource(FixedTimestepInfo {
+            step: self.step,
+            accumulator: self.accumulator,
+        });
     }
 }